@@ -0,0 +1,164 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult};
+
+use crate::msg::{AttestationResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, IssuerResponse, MigrateMsg, QueryMsg};
+use crate::state::{Attestation, State, ATTESTATIONS, ISSUERS, STATE};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let state = State {
+        owner: deps.api.addr_canonicalize(info.sender.as_str())?,
+    };
+
+    STATE.save(deps.storage, &state)?;
+
+    for issuer in msg.issuers {
+        ISSUERS.save(deps.storage, deps.api.addr_validate(&issuer)?.to_string(), &true)?;
+    }
+
+    Ok(Response::new())
+}
+
+/************************************ Migration *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+/************************************ Execution *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::AddIssuer { issuer } => execute_set_issuer(deps, info, issuer, true),
+        ExecuteMsg::RemoveIssuer { issuer } => execute_set_issuer(deps, info, issuer, false),
+        ExecuteMsg::Attest { address, jurisdiction, expiry } => execute_attest(deps, env, info, address, jurisdiction, expiry),
+        ExecuteMsg::Revoke { address } => execute_revoke(deps, info, address),
+        ExecuteMsg::UpdateOwner { owner } => execute_update_owner(deps, info, owner),
+    }
+}
+
+fn assert_owner(deps: Deps, info: &MessageInfo) -> StdResult<State> {
+    let state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    Ok(state)
+}
+
+fn assert_issuer(deps: Deps, info: &MessageInfo) -> StdResult<()> {
+    if !ISSUERS.may_load(deps.storage, info.sender.to_string())?.unwrap_or(false) {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    Ok(())
+}
+
+pub fn execute_set_issuer(deps: DepsMut, info: MessageInfo, issuer: String, approved: bool) -> StdResult<Response> {
+    assert_owner(deps.as_ref(), &info)?;
+
+    let issuer = deps.api.addr_validate(&issuer)?.to_string();
+    if approved {
+        ISSUERS.save(deps.storage, issuer.clone(), &true)?;
+    } else {
+        ISSUERS.remove(deps.storage, issuer.clone());
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("method", if approved { "add_issuer" } else { "remove_issuer" }),
+        ("issuer", issuer.as_str()),
+    ]))
+}
+
+pub fn execute_attest(deps: DepsMut, env: Env, info: MessageInfo, address: String, jurisdiction: String, expiry: u64) -> StdResult<Response> {
+    assert_issuer(deps.as_ref(), &info)?;
+
+    let address = deps.api.addr_validate(&address)?.to_string();
+    if jurisdiction.len() != 2 || !jurisdiction.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(StdError::generic_err("invalid jurisdiction code"));
+    }
+    if expiry <= env.block.time.seconds() {
+        return Err(StdError::generic_err("expiry should be in the future"));
+    }
+
+    let attestation = Attestation {
+        issuer: info.sender.to_string(),
+        jurisdiction: jurisdiction.to_ascii_uppercase(),
+        expiry,
+    };
+    ATTESTATIONS.save(deps.storage, address.clone(), &attestation)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("method", "attest"),
+        ("address", address.as_str()),
+        ("jurisdiction", attestation.jurisdiction.as_str()),
+        ("expiry", expiry.to_string().as_str()),
+    ]))
+}
+
+pub fn execute_revoke(deps: DepsMut, info: MessageInfo, address: String) -> StdResult<Response> {
+    assert_issuer(deps.as_ref(), &info)?;
+
+    ATTESTATIONS.remove(deps.storage, address.clone());
+
+    Ok(Response::new().add_attributes(vec![
+        ("method", "revoke"),
+        ("address", address.as_str()),
+    ]))
+}
+
+pub fn execute_update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
+    let mut state = assert_owner(deps.as_ref(), &info)?;
+
+    state.owner = deps.api.addr_canonicalize(&owner)?;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("method", "update_owner"),
+        ("owner", owner.as_str()),
+    ]))
+}
+
+/************************************ Query *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Issuer { issuer } => to_binary(&query_issuer(deps, issuer)?),
+        QueryMsg::Attestation { address } => to_binary(&query_attestation(deps, env, address)?),
+    }
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(ConfigResponse { owner: deps.api.addr_humanize(&state.owner)?.to_string() })
+}
+
+fn query_issuer(deps: Deps, issuer: String) -> StdResult<IssuerResponse> {
+    let approved = ISSUERS.may_load(deps.storage, issuer.clone())?.unwrap_or(false);
+    Ok(IssuerResponse { issuer, approved })
+}
+
+fn query_attestation(deps: Deps, env: Env, address: String) -> StdResult<AttestationResponse> {
+    let attestation = ATTESTATIONS.may_load(deps.storage, address.clone())?;
+    let valid = match &attestation {
+        Some(attestation) => attestation.expiry > env.block.time.seconds()
+            && ISSUERS.may_load(deps.storage, attestation.issuer.clone())?.unwrap_or(false),
+        None => false,
+    };
+    Ok(AttestationResponse { address, attestation, valid })
+}
@@ -0,0 +1,70 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::Attestation;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub issuers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Approve an issuer, owner only.
+    AddIssuer {
+        issuer: String,
+    },
+    /// Revoke an issuer, its attestations are no longer valid. Owner only.
+    RemoveIssuer {
+        issuer: String,
+    },
+    /// Record or renew the attestation of the address, approved issuers only.
+    Attest {
+        address: String,
+        jurisdiction: String,
+        expiry: u64,
+    },
+    /// Remove the attestation of the address, approved issuers only.
+    Revoke {
+        address: String,
+    },
+    UpdateOwner {
+        owner: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    Issuer {
+        issuer: String,
+    },
+    /// Attestation of the address and whether it is valid at the block time.
+    Attestation {
+        address: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IssuerResponse {
+    pub issuer: String,
+    pub approved: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AttestationResponse {
+    pub address: String,
+    pub attestation: Option<Attestation>,
+    // Recorded by a still approved issuer and not expired.
+    pub valid: bool,
+}
@@ -0,0 +1,27 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::CanonicalAddr;
+use cw_storage_plus::{Item, Map};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    // Owner address, manages the approved issuers
+    pub owner: CanonicalAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Attestation {
+    // Issuer which recorded the attestation.
+    pub issuer: String,
+    // ISO 3166-1 alpha-2 jurisdiction code, upper case.
+    pub jurisdiction: String,
+    // Timestamp after which the attestation is no longer valid.
+    pub expiry: u64,
+}
+
+pub const STATE: Item<State> = Item::new("state");
+
+pub const ISSUERS: Map<String, bool> = Map::new("issuers");
+
+pub const ATTESTATIONS: Map<String, Attestation> = Map::new("attestations");
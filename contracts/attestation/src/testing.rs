@@ -0,0 +1,51 @@
+use crate::contract::{execute, instantiate, query};
+use crate::msg::{AttestationResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Deps, Env, StdError, Timestamp};
+
+fn env_at(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(seconds);
+    env
+}
+
+fn attestation(deps: Deps, seconds: u64, address: &str) -> AttestationResponse {
+    from_binary(&query(deps, env_at(seconds), QueryMsg::Attestation { address: address.to_string() }).unwrap()).unwrap()
+}
+
+#[test]
+fn test_attestation() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg { issuers: vec!["issuer".to_string()] };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Attest { address: "user".to_string(), jurisdiction: "ch".to_string(), expiry: 2000 };
+    let res = execute(deps.as_mut(), env_at(1000), mock_info("user", &[]), msg.clone());
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "unauthorized"),
+        _ => panic!("Invalid error"),
+    }
+    execute(deps.as_mut(), env_at(1000), mock_info("issuer", &[]), msg).unwrap();
+
+    let res = attestation(deps.as_ref(), 1000, "user");
+    assert!(res.valid);
+    assert_eq!(res.attestation.unwrap().jurisdiction, "CH");
+
+    // expired
+    assert!(!attestation(deps.as_ref(), 2000, "user").valid);
+
+    // removing the issuer invalidates its attestations
+    let msg = ExecuteMsg::RemoveIssuer { issuer: "issuer".to_string() };
+    execute(deps.as_mut(), env_at(1000), mock_info("owner", &[]), msg).unwrap();
+    assert!(!attestation(deps.as_ref(), 1000, "user").valid);
+
+    let msg = ExecuteMsg::AddIssuer { issuer: "issuer".to_string() };
+    execute(deps.as_mut(), env_at(1000), mock_info("owner", &[]), msg).unwrap();
+    assert!(attestation(deps.as_ref(), 1000, "user").valid);
+
+    let msg = ExecuteMsg::Revoke { address: "user".to_string() };
+    execute(deps.as_mut(), env_at(1000), mock_info("issuer", &[]), msg).unwrap();
+    let res = attestation(deps.as_ref(), 1000, "user");
+    assert!(!res.valid);
+    assert_eq!(res.attestation, None);
+}
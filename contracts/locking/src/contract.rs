@@ -127,11 +127,18 @@ pub fn deposit(deps: DepsMut, env: Env, sender: Addr, amount: Uint128) -> StdRes
     let address = sender;
     let address_raw = deps.api.addr_canonicalize(&address.to_string())?;
 
-    let mut lock_info: LockInfo = read_lock_info(deps.storage, &address_raw).unwrap_or(LockInfo { amount: Uint128::zero(), unlock_started: 0 });
+    let mut lock_info: LockInfo = read_lock_info(deps.storage, &address_raw).unwrap_or(LockInfo { amount: Uint128::zero(), unlock_started: 0, locked_at: 0 });
 
     if lock_info.amount.is_zero() {
         lock_info.unlock_started = 0;
     }
+    // new tokens are locked from now, so topping up lowers the lock age
+    lock_info.locked_at = if lock_info.amount.is_zero() || lock_info.locked_at == 0 {
+        current_time
+    } else {
+        let weighted = lock_info.amount * Uint128::from(lock_info.locked_at) + amount * Uint128::from(current_time);
+        (weighted / (lock_info.amount + amount)).u128() as u64
+    };
     lock_info.amount = lock_info.amount + amount;
     store_lock_info(deps.storage, &address_raw, &lock_info)?;
 
@@ -163,7 +170,7 @@ pub fn unlock(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response>
     ]))
 }
 
-pub fn reset_timer(deps: DepsMut, _env: Env, info: MessageInfo) -> StdResult<Response> {
+pub fn reset_timer(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
     let address = info.sender;
     let address_raw = deps.api.addr_canonicalize(&address.to_string())?;
 
@@ -173,6 +180,8 @@ pub fn reset_timer(deps: DepsMut, _env: Env, info: MessageInfo) -> StdResult<Res
         return Err(StdError::generic_err("Unlock not started"));
     }
     lock_info.unlock_started = 0;
+    // lock age starts over after an unlock
+    lock_info.locked_at = env.block.time.seconds();
     store_lock_info(deps.storage, &address_raw, &lock_info)?;
 
     Ok(Response::new().add_attributes(vec![
@@ -275,7 +284,7 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
 }
 
 pub fn query_lock_account(deps: Deps, env: Env, address: String) -> StdResult<LockInfoResponse> {
-    let info = read_lock_info(deps.storage, &deps.api.addr_canonicalize(&address)?).unwrap_or(LockInfo { amount: Uint128::zero(), unlock_started: 0 });
+    let info = read_lock_info(deps.storage, &deps.api.addr_canonicalize(&address)?).unwrap_or(LockInfo { amount: Uint128::zero(), unlock_started: 0, locked_at: 0 });
     let penalty_amount = compute_penalty_amount(info.amount, env.block.time.seconds(), &info);
     let resp = LockInfoResponse { address, info, penalty: penalty_amount };
 
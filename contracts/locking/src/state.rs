@@ -31,6 +31,9 @@ pub struct LockInfo {
     pub amount: Uint128,
     // Unlock start timestamp.
     pub unlock_started: u64,
+    // Lock start timestamp, averaged by amount over deposits. Zero for positions locked before it was tracked.
+    #[serde(default)]
+    pub locked_at: u64,
 }
 
 pub fn read_lock_info(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<LockInfo> {
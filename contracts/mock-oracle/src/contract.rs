@@ -0,0 +1,77 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, PriceResponse, QueryMsg};
+use crate::state::{PriceInfo, State, PRICES, STATE};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let state = State {
+        owner: deps.api.addr_canonicalize(info.sender.as_str())?,
+    };
+
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new())
+}
+
+/************************************ Migration *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+/************************************ Execution *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetPrice { asset, price } => execute_set_price(deps, env, info, asset, price),
+    }
+}
+
+pub fn execute_set_price(deps: DepsMut, env: Env, info: MessageInfo, asset: String, price: Decimal) -> StdResult<Response> {
+    let state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    PRICES.save(deps.storage, asset.clone(), &PriceInfo {
+        price,
+        last_updated: env.block.time.seconds(),
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("method", "set_price"),
+        ("asset", asset.as_str()),
+        ("price", price.to_string().as_str()),
+    ]))
+}
+
+/************************************ Query *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Price { asset } => to_binary(&query_price(deps, asset)?),
+    }
+}
+
+fn query_price(deps: Deps, asset: String) -> StdResult<PriceResponse> {
+    let price_info = PRICES.load(deps.storage, asset)?;
+    Ok(PriceResponse { price: price_info.price, last_updated: price_info.last_updated })
+}
@@ -0,0 +1,23 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{CanonicalAddr, Decimal};
+use cw_storage_plus::{Item, Map};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    // Owner address, the only price feeder
+    pub owner: CanonicalAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceInfo {
+    // USD price of one whole unit of the asset.
+    pub price: Decimal,
+    // Timestamp of the last price update.
+    pub last_updated: u64,
+}
+
+pub const STATE: Item<State> = Item::new("state");
+
+pub const PRICES: Map<String, PriceInfo> = Map::new("prices");
@@ -0,0 +1,25 @@
+use crate::contract::{execute, instantiate, query};
+use crate::msg::{ExecuteMsg, InstantiateMsg, PriceResponse, QueryMsg};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Decimal, StdError};
+
+#[test]
+fn test_set_price() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg {}).unwrap();
+
+    let msg = ExecuteMsg::SetPrice { asset: "uluna".to_string(), price: Decimal::percent(150) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("user", &[]), msg.clone());
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "unauthorized"),
+        _ => panic!("Invalid error"),
+    }
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let price: PriceResponse = from_binary(
+        &query(deps.as_ref(), mock_env(), QueryMsg::Price { asset: "uluna".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(price.price, Decimal::percent(150));
+    assert_eq!(price.last_updated, mock_env().block.time.seconds());
+}
@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use presale::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ParticipantsCountResponse, GetSaleStatusResponse, GetParticipantResponse, GetParticipantsResponse, PresaleInfoResponse};
+use presale::msg::{ExecuteMsg, Cw20HookMsg, InstantiateMsg, QueryMsg, ParticipantsCountResponse, GetSaleStatusResponse, GetParticipantResponse, GetParticipantsResponse, PresaleInfoResponse, RoundResponse, RoundsResponse, SimulateDepositResponse, GetBidResponse, StakingTierResponse, LockAllocationResponse, LotteryEntryResponse, WhitelistResponse, TokenAllocationResponse, UsedTokenResponse};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,9 +14,20 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(ParticipantsCountResponse), &out_dir);
     export_schema(&schema_for!(GetSaleStatusResponse), &out_dir);
     export_schema(&schema_for!(GetParticipantResponse), &out_dir);
     export_schema(&schema_for!(GetParticipantsResponse), &out_dir);
     export_schema(&schema_for!(PresaleInfoResponse), &out_dir);
+    export_schema(&schema_for!(RoundResponse), &out_dir);
+    export_schema(&schema_for!(RoundsResponse), &out_dir);
+    export_schema(&schema_for!(SimulateDepositResponse), &out_dir);
+    export_schema(&schema_for!(GetBidResponse), &out_dir);
+    export_schema(&schema_for!(StakingTierResponse), &out_dir);
+    export_schema(&schema_for!(LockAllocationResponse), &out_dir);
+    export_schema(&schema_for!(LotteryEntryResponse), &out_dir);
+    export_schema(&schema_for!(WhitelistResponse), &out_dir);
+    export_schema(&schema_for!(TokenAllocationResponse), &out_dir);
+    export_schema(&schema_for!(UsedTokenResponse), &out_dir);
 }
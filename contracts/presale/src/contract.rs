@@ -2,14 +2,17 @@ use std::convert::TryInto;
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, CosmosMsg, WasmMsg, Uint128, WasmQuery, QueryRequest, attr, BankMsg, Coin};
-use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, BalanceResponse };
+use cosmwasm_std::{to_binary, from_binary, Api, Binary, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult, Storage, Order, CosmosMsg, WasmMsg, Uint128, attr, BankMsg, Coin};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use sha2::Digest;
 
+use crate::curve::{CurveParams, curve_cost, curve_reward_amount};
+use crate::drand::{beacon_randomness, draw_winners, parse_public_key, verify_beacon};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ParticipantsCountResponse, GetParticipantResponse, GetParticipantsResponse, GetSaleStatusResponse, MigrateMsg, PresaleInfoResponse};
-use crate::querier::{query_decimals, query_balance};
-use crate::state::{PARTICIPANTS, PRIVATE_SOLD_FUNDS, ACCURACY, State, Participant, AlloInfo, store_state, read_state};
+use crate::msg::{ExecuteMsg, Cw20HookMsg, InstantiateMsg, QueryMsg, ParticipantsCountResponse, GetParticipantResponse, GetParticipantsResponse, GetSaleStatusResponse, MigrateMsg, PresaleInfoResponse, OracleInfo, StakingInfo, StakingTierResponse, LockingInfo, LockAllocationResponse, LotteryEntryResponse, TokenAllocationResponse, UsedTokenResponse, BlockedResponse, NftGateInfo, AttestationInfo, WithdrawalPenaltyInfo, FinalizeInfo, ProceedsReceiverInfo, WhitelistEntry, WhitelistEntryResponse, WhitelistResponse, WhitelistPermit, RoundMsg, RoundResponse, RoundsResponse, SimulateDepositResponse, GetBidResponse};
+use crate::querier::{query_decimals, query_balance, query_token_balance, query_price, query_bond_amount, query_lock_info, query_attestation, query_vesting_blocked, query_nft_owner, query_nft_tokens};
+use crate::state::{BIDS, BID_DEMAND, ESCROW_PROCEEDS, PARTICIPANTS, PARTICIPANT_FUNDS, PRIVATE_SOLD_FUNDS, ROUND_FUNDS, ROUND_REWARDS, STAKE_SNAPSHOTS, TIER_RESERVATIONS, USED_PERMITS, USED_TOKENS, BLOCKLIST, TOKEN_ALLOCATIONS, WHITELIST, LOTTERY_TICKETS, LOTTERY_WINNERS, MAX_LOTTERY_ENTRANTS, DEFAULT_LIMIT, MAX_LIMIT, ACCURACY, USD_DECIMALS, State, Participant, AlloInfo, Bid, FundAsset, OracleConfig, StakingTiers, LockingWeights, Lottery, AttestationGate, BlockInfo, NftGate, WithdrawalPenalty, FinalizeConfig, ProceedsReceiver, TicketSource, PricingCurve, PublicWhitelist, SignatureScheme, WhitelistMode, WhitelistSigner, Round, RoundKind, SaleMode, SaleOutcome, store_state, read_state, read_whitelist};
+use crate::types::AssetInfo;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -18,21 +21,210 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    if msg.fund_assets.is_empty() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    let oracle = match msg.oracle {
+        Some(oracle) => {
+            if oracle.token_price.is_zero() {
+                return Err(ContractError::InvalidInput {});
+            }
+            Some(OracleConfig {
+                contract: deps.api.addr_canonicalize(&oracle.contract)?,
+                token_price: oracle.token_price,
+                max_price_age: oracle.max_price_age,
+            })
+        },
+        None => None,
+    };
+
+    let mut fund_assets: Vec<FundAsset> = vec![];
+    for asset in msg.fund_assets {
+        let (asset_info, decimals) = match asset.info {
+            AssetInfo::Token { contract_addr } => {
+                let contract_addr = deps.api.addr_validate(&contract_addr)?.to_string();
+                let decimals = match asset.decimals {
+                    Some(decimals) => decimals,
+                    None => query_decimals(deps.as_ref(), contract_addr.clone())?,
+                };
+                (AssetInfo::Token { contract_addr }, decimals)
+            },
+            AssetInfo::NativeToken { denom } => (AssetInfo::NativeToken { denom }, asset.decimals.unwrap_or(6)),
+        };
+
+        // exchange rates are not used when assets are priced by the oracle
+        if (oracle.is_none() && asset.exchange_rate.is_zero()) || fund_assets.iter().any(|a| a.info == asset_info) {
+            return Err(ContractError::InvalidInput {});
+        }
+
+        fund_assets.push(FundAsset {
+            info: asset_info,
+            exchange_rate: asset.exchange_rate,
+            decimals,
+            cap: asset.cap,
+            private_raised: Uint128::zero(),
+            public_raised: Uint128::zero(),
+        });
+    }
+
+    match msg.sale_mode {
+        SaleMode::DutchAuction { start_price, floor_price } if floor_price.is_zero() || start_price <= floor_price => {
+            return Err(ContractError::InvalidInput {});
+        },
+        SaleMode::SealedBid { reserve_price, .. } if reserve_price.is_zero() => {
+            return Err(ContractError::InvalidInput {});
+        },
+        SaleMode::FairLaunch { target_raise } if target_raise.is_zero() => {
+            return Err(ContractError::InvalidInput {});
+        },
+        _ => {},
+    }
+
+    if let Some(signer) = &msg.whitelist_signer {
+        validate_signer(signer)?;
+    }
+
+    let rounds = msg.rounds.into_iter()
+        .enumerate()
+        .map(|(index, round)| build_round(index as u64 + 1, round))
+        .collect::<Result<Vec<Round>, ContractError>>()?;
+    validate_rounds(&rounds)?;
+
+    let staking = match msg.staking {
+        Some(staking) => {
+            // tiers must be ordered and snapshotted before the sale starts
+            if staking.tiers.is_empty()
+                || staking.tiers.iter().any(|tier| tier.multiplier.is_zero())
+                || staking.tiers.windows(2).any(|pair| pair[0].min_bond >= pair[1].min_bond)
+                || rounds.first().is_some_and(|round| staking.snapshot_time > round.start_time) {
+                return Err(ContractError::InvalidInput {});
+            }
+            Some(StakingTiers {
+                contract: deps.api.addr_canonicalize(&staking.contract)?,
+                snapshot_time: staking.snapshot_time,
+                base_allocation: staking.base_allocation,
+                tiers: staking.tiers,
+            })
+        },
+        None => None,
+    };
+
+    let locking = match msg.locking {
+        Some(locking) => {
+            if locking.allocation_rate.is_zero() || locking.max_boost < Decimal::one() {
+                return Err(ContractError::InvalidInput {});
+            }
+            Some(LockingWeights {
+                contract: deps.api.addr_canonicalize(&locking.contract)?,
+                allocation_rate: locking.allocation_rate,
+                min_lock_age: locking.min_lock_age,
+                max_boost: locking.max_boost,
+                boost_period: locking.boost_period,
+            })
+        },
+        None => None,
+    };
+
+    let lottery = match msg.lottery {
+        Some(lottery) => {
+            let public_key = hex::decode(&lottery.drand_public_key)?;
+            if lottery.ticket_size.is_zero() || lottery.winners == 0 || lottery.drand_period == 0 || lottery.registration_end < lottery.drand_genesis_time || parse_public_key(&public_key).is_none() {
+                return Err(ContractError::InvalidInput {});
+            }
+            let ticket_source = match lottery.ticket_source {
+                TicketSource::Staking { contract } => TicketSource::Staking { contract: deps.api.addr_validate(&contract)?.to_string() },
+                TicketSource::Locking { contract } => TicketSource::Locking { contract: deps.api.addr_validate(&contract)?.to_string() },
+            };
+            Some(Lottery {
+                ticket_source,
+                ticket_size: lottery.ticket_size,
+                winners: lottery.winners,
+                allocation: lottery.allocation,
+                registration_end: lottery.registration_end,
+                drand_public_key: lottery.drand_public_key,
+                drand_genesis_time: lottery.drand_genesis_time,
+                drand_period: lottery.drand_period,
+                total_tickets: Uint128::zero(),
+                entrants: 0,
+                beacon_round: None,
+                randomness: None,
+            })
+        },
+        None => None,
+    };
+
+    let attestation = match msg.attestation {
+        Some(attestation) => Some(attestation_gate(deps.as_ref(), attestation)?),
+        None => None,
+    };
+
+    let nft_gate = match msg.nft_gate {
+        Some(nft_gate) => {
+            if nft_gate.allocation_per_token.is_zero() {
+                return Err(ContractError::InvalidInput {  });
+            }
+            Some(NftGate {
+                collection: deps.api.addr_canonicalize(&nft_gate.collection)?,
+                allocation_per_token: nft_gate.allocation_per_token,
+            })
+        },
+        None => None,
+    };
+
+    let withdrawal_penalty = match msg.withdrawal_penalty {
+        Some(withdrawal_penalty) => Some(validate_withdrawal_penalty(deps.as_ref(), withdrawal_penalty)?),
+        None => None,
+    };
+
+    let finalize = match msg.finalize {
+        Some(finalize) => Some(validate_finalize(deps.as_ref(), finalize)?),
+        None => None,
+    };
+
     let state = State {
         owner: deps.api.addr_canonicalize(info.sender.as_str())?,
-        fund_denom: msg.fund_denom,
+        fund_assets,
         reward_token: deps.api.addr_canonicalize(msg.reward_token.as_str())?,
         vesting: deps.api.addr_canonicalize(msg.vesting.as_str())?,
         whitelist_merkle_root: msg.whitelist_merkle_root,
-
-        exchange_rate: msg.exchange_rate,
-        presale_period: msg.presale_period,
-        public_start_time: msg.public_start_time,
-        private_start_time: msg.private_start_time,
+        legacy_whitelist_until: msg.legacy_whitelist_until,
+        public_whitelist: msg.public_whitelist,
+        whitelist_mode: msg.whitelist_mode,
+        whitelist_signer: msg.whitelist_signer,
+        oracle,
+        staking,
+        locking,
+        lottery,
+        attestation,
+        compliance: None,
+        guardian: match msg.guardian {
+            Some(guardian) => Some(deps.api.addr_canonicalize(&guardian)?),
+            None => None,
+        },
+        paused_at: None,
+        extend_on_pause: msg.extend_on_pause,
+        nft_gate,
+        withdrawal_penalty,
+        finalize,
+
+        sale_mode: msg.sale_mode,
+        rounds,
         total_rewards_amount: msg.total_rewards_amount,
+        soft_cap: msg.soft_cap,
 
         private_sold_amount: Uint128::zero(),
         public_sold_amount: Uint128::zero(),
+        total_raised: Uint128::zero(),
+        escrowed_rewards: Uint128::zero(),
+        clearing_price: None,
+        fill_ratio: None,
+        proceeds_withdrawn: false,
+        cancelled: false,
+        finalized: false,
+        registered_participants: 0,
+        frozen_rewards: Uint128::zero(),
+        reserved_allocations: Uint128::zero(),
         userlist: vec![],
     };
 
@@ -57,6 +249,12 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    /* Finalized presale only settles participants */
+    let finalized = read_state(deps.storage)?.finalized;
+    if finalized && !matches!(msg, ExecuteMsg::Claim {} | ExecuteMsg::ClaimRefund {} | ExecuteMsg::Block { .. } | ExecuteMsg::Unblock { .. } | ExecuteMsg::RecoverFrozen { .. } | ExecuteMsg::Pause {} | ExecuteMsg::Unpause {}) {
+        return Err(ContractError::Finalized {  });
+    }
+
     match msg {
         ExecuteMsg::TransferOwnerShip {
             new_owner
@@ -64,19 +262,143 @@ pub fn execute(
 
         ExecuteMsg::SetMerkleRoot { merkle_root } => execute_set_whitelist_merkle_root(deps, info, merkle_root),
 
-        ExecuteMsg::UpdatePresaleInfo {
-            new_private_start_time,
-            new_public_start_time,
-            new_presale_period
-        } => execute_update_info(deps, env, info, new_private_start_time, new_public_start_time, new_presale_period),
+        ExecuteMsg::SetPublicWhitelist { public_whitelist } => execute_set_public_whitelist(deps, info, public_whitelist),
+
+        ExecuteMsg::UpdateRound { round_id, round } => execute_update_round(deps, env, info, round_id, round),
 
-        ExecuteMsg::Deposit { allo_info, proof } => execute_deposit(deps, env, info, allo_info, proof),
+        ExecuteMsg::SetWhitelistSigner { signer } => execute_set_whitelist_signer(deps, info, signer),
+        ExecuteMsg::SetWhitelistMode { whitelist_mode } => execute_set_whitelist_mode(deps, info, whitelist_mode),
+        ExecuteMsg::UpdateWhitelist { add, remove } => execute_update_whitelist(deps, info, add, remove),
+        ExecuteMsg::SetGuardian { guardian } => execute_set_guardian(deps, info, guardian),
+        ExecuteMsg::Pause {} => execute_pause(deps, env, info),
+        ExecuteMsg::Unpause {} => execute_unpause(deps, env, info),
+        ExecuteMsg::SetCompliance { compliance } => execute_set_compliance(deps, info, compliance),
+        ExecuteMsg::Block { address, reason } => execute_block(deps, env, info, address, reason),
+        ExecuteMsg::Unblock { address, reason } => execute_unblock(deps, info, address, reason),
+        ExecuteMsg::RecoverFrozen { address, recovery } => execute_recover_frozen(deps, env, info, address, recovery),
+        ExecuteMsg::SetAttestation { attestation } => execute_set_attestation(deps, info, attestation),
+        ExecuteMsg::SetWithdrawalPenalty { withdrawal_penalty } => execute_set_withdrawal_penalty(deps, info, withdrawal_penalty),
+        ExecuteMsg::SetFinalizeConfig { finalize } => execute_set_finalize_config(deps, info, finalize),
 
-        ExecuteMsg::DepositPrivateSale { allo_info, proof } => execute_deposit_private_sale(deps, env, info, allo_info, proof),
+        ExecuteMsg::Deposit { allo_info, proof, permit } => {
+            let (asset_info, amount) = native_fund(&info)?;
+            execute_deposit(deps, env, info.sender.to_string(), asset_info, amount, allo_info, proof, permit)
+        },
 
+        ExecuteMsg::RegisterStake {} => execute_register_stake(deps, env, info),
+
+        ExecuteMsg::RegisterLottery {} => execute_register_lottery(deps, env, info),
+        ExecuteMsg::ClaimTokenAllocation { token_ids } => execute_claim_token_allocation(deps, env, info, token_ids),
+
+        ExecuteMsg::SubmitBeacon { round, previous_signature, signature } => execute_submit_beacon(deps, round, previous_signature, signature),
+
+        ExecuteMsg::DepositPrivateSale { allo_info, proof, permit } => {
+            let (asset_info, amount) = native_fund(&info)?;
+            execute_deposit_private_sale(deps, env, info.sender.to_string(), asset_info, amount, allo_info, proof, permit)
+        },
+
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+
+        ExecuteMsg::WithdrawContribution { amount } => execute_withdraw_contribution(deps, env, info, amount),
         ExecuteMsg::WithdrawFunds { receiver } => execute_withdraw_funds(deps, env, info, receiver),
 
         ExecuteMsg::WithdrawUnsoldToken { receiver } => execute_withdraw_unsold_token(deps, env, info, receiver),
+
+        ExecuteMsg::CancelSale {} => execute_cancel_sale(deps, info),
+        ExecuteMsg::Finalize {} => execute_finalize(deps, env),
+        ExecuteMsg::ClaimRefund {} => execute_claim_refund(deps, env, info),
+
+        ExecuteMsg::Claim {} => execute_claim(deps, env, info),
+        ExecuteMsg::RegisterAllocations { limit } => execute_register_allocations(deps, env, limit),
+
+        ExecuteMsg::CommitBid { commitment } => {
+            let (asset_info, amount) = native_fund(&info)?;
+            execute_commit_bid(deps, env, info.sender.to_string(), asset_info, amount, commitment)
+        },
+
+        ExecuteMsg::RevealBid { price, amount, salt } => execute_reveal_bid(deps, env, info, price, amount, salt),
+
+        ExecuteMsg::Settle {} => execute_settle(deps, env),
+    }
+}
+
+pub fn receive_cw20(deps: DepsMut, env: Env, info: MessageInfo, cw20_msg: Cw20ReceiveMsg) -> Result<Response, ContractError> {
+    if cw20_msg.amount.is_zero() {
+        return Err(ContractError::Funds {  });
+    }
+
+    // fund token is checked against accepted assets on deposit
+    let asset_info = AssetInfo::Token { contract_addr: info.sender.to_string() };
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?.to_string();
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Deposit { allo_info, proof, permit } => execute_deposit(deps, env, sender, asset_info, cw20_msg.amount, allo_info, proof, permit),
+        Cw20HookMsg::DepositPrivateSale { allo_info, proof, permit } => execute_deposit_private_sale(deps, env, sender, asset_info, cw20_msg.amount, allo_info, proof, permit),
+        Cw20HookMsg::CommitBid { commitment } => execute_commit_bid(deps, env, sender, asset_info, cw20_msg.amount, commitment),
+        Cw20HookMsg::FundRewards {} => execute_fund_rewards(deps, info, cw20_msg.amount),
+    }
+}
+
+pub fn execute_fund_rewards(deps: DepsMut, info: MessageInfo, amount: Uint128) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.reward_token {
+        return Err(ContractError::InvalidInput {  });
+    }
+    if state.cancelled {
+        return Err(ContractError::SaleCancelled {  });
+    }
+
+    state.escrowed_rewards += amount;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("method", "fund_rewards"),
+        attr("amount", amount.to_string()),
+    ]))
+}
+
+/// Reward token transfer out of the escrow held by presale.
+fn reward_transfer_msg(state: &State, api: &dyn Api, recipient: String, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: api.addr_humanize(&state.reward_token)?.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+        funds: vec![],
+    }))
+}
+
+/// Returns native coin sent with the message.
+fn native_fund(info: &MessageInfo) -> Result<(AssetInfo, Uint128), ContractError> {
+    match info.funds.as_slice() {
+        [coins] if !coins.amount.is_zero() => Ok((AssetInfo::NativeToken { denom: coins.denom.clone() }, coins.amount)),
+        _ => Err(ContractError::Funds {  }),
+    }
+}
+
+/// Returns index of the accepted fund asset.
+fn fund_asset_index(state: &State, asset_info: &AssetInfo) -> Result<usize, ContractError> {
+    state.fund_assets.iter()
+        .position(|asset| asset.info == *asset_info)
+        .ok_or(ContractError::Funds {  })
+}
+
+/// Builds the message that sends `amount` of fund asset to `recipient`.
+pub fn fund_transfer_msg(asset_info: &AssetInfo, recipient: String, amount: Uint128) -> StdResult<CosmosMsg> {
+    match asset_info {
+        AssetInfo::NativeToken { denom } => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient,
+            amount: vec![Coin {
+                denom: denom.to_string(),
+                amount,
+            }]
+        })),
+        AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient,
+                amount,
+            })?,
+            funds: vec![],
+        })),
     }
 }
 
@@ -115,7 +437,7 @@ pub fn execute_set_whitelist_merkle_root(deps: DepsMut, info: MessageInfo, merkl
     ]))
 }
 
-pub fn execute_update_info(deps: DepsMut, env: Env, info: MessageInfo, new_private_start_time: u64, new_public_start_time: u64, new_presale_period: u64) -> Result<Response, ContractError> {
+pub fn execute_set_public_whitelist(deps: DepsMut, info: MessageInfo, public_whitelist: PublicWhitelist) -> Result<Response, ContractError> {
     let mut state: State = read_state(deps.storage)?;
 
     // permission check
@@ -123,290 +445,2135 @@ pub fn execute_update_info(deps: DepsMut, env: Env, info: MessageInfo, new_priva
         return Err(ContractError::Unauthorized {});
     }
 
-    if new_private_start_time < env.block.time.seconds() ||  new_public_start_time < env.block.time.seconds() {
-        return Err(ContractError::InvalidInput {});
+    state.public_whitelist = public_whitelist;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_public_whitelist"),
+    ]))
+}
+
+pub fn execute_set_whitelist_mode(deps: DepsMut, info: MessageInfo, whitelist_mode: WhitelistMode) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
 
-    state.private_start_time = new_private_start_time;
-    state.public_start_time = new_public_start_time;
-    state.presale_period = new_presale_period;
+    state.whitelist_mode = whitelist_mode;
     store_state(deps.storage, &state)?;
 
     Ok(Response::new().add_attributes(vec![
-        attr("action", "set_start_time"),
-        attr("new_private_start_time", new_private_start_time.to_string()),
-        attr("new_public_start_time", new_public_start_time.to_string()),
-        attr("new_presale_period", new_presale_period.to_string()),
+        attr("action", "set_whitelist_mode"),
     ]))
 }
 
-pub fn calc_reward_amount(deps: Deps, state: State, fund_amount: Uint128) -> StdResult<Uint128> {
-    let fund_decimals: u32 = 6;
-    let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
+// Max on-chain whitelist entries edited by one message.
+const MAX_WHITELIST_BATCH: usize = 500;
+
+pub fn execute_update_whitelist(deps: DepsMut, info: MessageInfo, add: Vec<WhitelistEntry>, remove: Vec<String>) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if add.len() + remove.len() > MAX_WHITELIST_BATCH {
+        return Err(ContractError::InvalidInput {  });
+    }
 
-    Ok(fund_amount
-        .checked_mul(Uint128::from(ACCURACY)).unwrap()
-        .checked_div(state.exchange_rate).unwrap()
-        .checked_mul(Uint128::from(10u128).wrapping_pow(reward_decimals)).unwrap()
-        .checked_div(Uint128::from(10u128).wrapping_pow(fund_decimals)).unwrap()
-    )
+    /* Existing entries are overwritten */
+    for entry in &add {
+        let address = deps.api.addr_validate(&entry.address)?;
+        WHITELIST.save(deps.storage, address.to_string(), &entry.allo_info)?;
+    }
+    for address in &remove {
+        WHITELIST.remove(deps.storage, address.clone());
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_whitelist"),
+        attr("added", add.len().to_string()),
+        attr("removed", remove.len().to_string()),
+    ]))
 }
 
-pub fn verify_whitelist(state: State, sender: &String, allo_info: &AlloInfo, proof: &Vec<String>) -> Result<bool, ContractError> {
-    let user_input = format!("{}{}{}", sender, allo_info.private_allocation, allo_info.public_allocation);
-    let hash = sha2::Sha256::digest(user_input.as_bytes())
-        .as_slice()
-        .try_into()
-        .map_err(|_| ContractError::WrongLength {})?;
+fn build_round(id: u64, msg: RoundMsg) -> Result<Round, ContractError> {
+    if msg.start_time >= msg.end_time || msg.exchange_rate == Some(Uint128::zero()) {
+        return Err(ContractError::InvalidInput {});
+    }
+    if let Some(PricingCurve::Exponential { growth, step }) = &msg.curve {
+        if *growth <= Decimal::one() || step.is_zero() {
+            return Err(ContractError::InvalidInput {});
+        }
+    }
 
-    let hash = proof.into_iter().try_fold(hash, |hash, p| {
-        let mut proof_buf = [0; 32];
-        hex::decode_to_slice(p, &mut proof_buf)?;
-        let mut hashes = [hash, proof_buf];
-        hashes.sort_unstable();
-        sha2::Sha256::digest(&hashes.concat())
-            .as_slice()
-            .try_into()
-            .map_err(|_| ContractError::WrongLength {})
-    })?;
+    Ok(Round {
+        id,
+        kind: msg.kind,
+        start_time: msg.start_time,
+        end_time: msg.end_time,
+        exchange_rate: msg.exchange_rate,
+        curve: msg.curve,
+        merkle_root: msg.merkle_root,
+        cap: msg.cap,
+        wallet_cap: msg.wallet_cap,
+        sold_amount: Uint128::zero(),
+        raised: Uint128::zero(),
+    })
+}
 
-    let mut root_buf: [u8; 32] = [0; 32];
-    hex::decode_to_slice(state.whitelist_merkle_root.clone(), &mut root_buf)?;
-    Ok(root_buf == hash)
+/// Rounds must be ordered by time and must not overlap.
+fn validate_rounds(rounds: &[Round]) -> Result<(), ContractError> {
+    if rounds.is_empty() || rounds.windows(2).any(|pair| pair[0].end_time > pair[1].start_time) {
+        return Err(ContractError::InvalidInput {});
+    }
+    Ok(())
 }
 
-pub fn execute_deposit(deps: DepsMut, env: Env, info: MessageInfo, allo_info: AlloInfo, proof: Vec<String>) -> Result<Response, ContractError> {
+pub fn execute_update_round(deps: DepsMut, env: Env, info: MessageInfo, round_id: u64, round_msg: RoundMsg) -> Result<Response, ContractError> {
     let mut state: State = read_state(deps.storage)?;
-    let sender = info.sender.to_string();
 
-    /* Check if Presale in progress */
-    let end_time = state.public_start_time + state.presale_period;
-    if env.block.time.seconds() > end_time || env.block.time.seconds() < state.public_start_time {
-        return Err(ContractError::PublicNotInProgress {});
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
 
-    /* Check fund tokens */
-    let amount;
-    if let Some(coins) = info.funds.first() {
-        if coins.denom != state.fund_denom || coins.amount.is_zero() {
-            return Err(ContractError::Funds {  });
-        }
-        amount = coins.amount;
-    } else {
-        return Err(ContractError::Funds {  });
+    let now = env.block.time.seconds();
+    let index = state.rounds.iter()
+        .position(|round| round.id == round_id)
+        .ok_or(ContractError::InvalidInput {})?;
+    let current = &state.rounds[index];
+
+    // ended rounds can't be changed, started rounds keep their kind and start time
+    if current.end_time <= now || round_msg.end_time <= now {
+        return Err(ContractError::InvalidInput {});
+    }
+    let started = current.start_time <= now;
+    if (started && (round_msg.start_time != current.start_time || round_msg.kind != current.kind))
+        || (!started && round_msg.start_time < now) {
+        return Err(ContractError::InvalidInput {});
     }
 
-    /* Verify if whitelisted */
-    // if state.whitelist_merkle_root.len() > 0 {
-    //     if verify_whitelist(state.clone(), &sender, &allo_info, &proof)? == false {
-    //         return Err(ContractError::NotWhitelisted {});
-    //     }
-    // }
+    let mut round = build_round(round_id, round_msg)?;
+    round.sold_amount = current.sold_amount;
+    round.raised = current.raised;
+    state.rounds[index] = round.clone();
+    validate_rounds(&state.rounds)?;
+    store_state(deps.storage, &state)?;
 
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_round"),
+        attr("round_id", round_id.to_string()),
+        attr("start_time", round.start_time.to_string()),
+        attr("end_time", round.end_time.to_string()),
+    ]))
+}
 
+/// Returns index of the round active at `now`.
+pub fn active_round_index(state: &State, now: u64) -> Option<usize> {
+    state.rounds.iter().position(|round| round.start_time <= now && now < round.end_time)
+}
 
-    let mut recp_info = Participant {
-        fund_balance: Uint128::zero(),
-        reward_balance: Uint128::zero()
-    };
-    let mut private_sold_fund = Uint128::zero();
+/// Presale ends with its last round.
+pub fn sale_end_time(state: &State) -> u64 {
+    state.rounds.last().map(|round| round.end_time).unwrap_or_default()
+}
 
-    /* Add to participants list */
-    if PARTICIPANTS.has(deps.storage, sender.clone()) {
-        recp_info = PARTICIPANTS.load(deps.storage, sender.clone())?;
-        private_sold_fund = PRIVATE_SOLD_FUNDS.load(deps.storage, sender.clone())?;
-    } else {
-        state.userlist.push(sender.clone());
+/// Reward token price of the round = fund value / reward * ACCURACY.
+pub fn round_price(state: &State, round: &Round) -> Uint128 {
+    match (round.exchange_rate, &state.oracle) {
+        (Some(exchange_rate), _) => exchange_rate,
+        (None, Some(oracle)) => oracle.token_price,
+        (None, None) => state.fund_assets[0].exchange_rate,
     }
+}
 
-    /* Check allocation */
-    let new_fund_balance = recp_info.fund_balance + amount;
-    // if allo_info.public_allocation + private_sold_fund < new_fund_balance {
-    //     return Err(ContractError::ExceedAllocation {  });
-    // }
+/// Decimals of fund value, USD in oracle mode or the first fund asset otherwise.
+fn value_decimals(state: &State) -> u32 {
+    match state.oracle {
+        Some(_) => USD_DECIMALS,
+        None => state.fund_assets[0].decimals,
+    }
+}
 
-    /* Update rewards amount */
-    let reward_amount = calc_reward_amount(deps.as_ref(), state.clone(), amount)?;
-    recp_info.fund_balance = new_fund_balance;
-    recp_info.reward_balance = recp_info.reward_balance + reward_amount;
-    state.public_sold_amount = state.public_sold_amount + reward_amount;
+/// Round with the price deposits are filled at.
+/// Auction bids are priced at the current auction price instead of the round price.
+pub fn deposit_round(state: &State, round_index: usize, now: u64) -> Round {
+    let mut round = state.rounds[round_index].clone();
+    if let SaleMode::DutchAuction { start_price, floor_price } = state.sale_mode {
+        round.exchange_rate = Some(auction_price(state, start_price, floor_price, now));
+        round.curve = None;
+    }
+    round
+}
 
-    store_state(deps.storage, &state)?;
-    PARTICIPANTS.save(deps.storage, sender.clone(), &recp_info)?;
+/// Auction price decays linearly from the start price to the floor price over the sale.
+pub fn auction_price(state: &State, start_price: Uint128, floor_price: Uint128, now: u64) -> Uint128 {
+    let start_time = state.rounds[0].start_time;
+    let end_time = sale_end_time(state);
+    if now <= start_time {
+        return start_price;
+    }
+    if now >= end_time {
+        return floor_price;
+    }
+    start_price - (start_price - floor_price).multiply_ratio(now - start_time, end_time - start_time)
+}
 
-    /* Update vesting */
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
-        msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
-            recp: sender.clone(),
-            amount: recp_info.reward_balance.u128().try_into().unwrap(),
-        })?,
-        funds: vec![],
-    }));
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("method", "deposit"))
+/// Uniform price paid by all auction bidders, known once the supply sells out or the sale ends.
+pub fn clearing_price(state: &State, now: u64) -> Option<Uint128> {
+    match state.sale_mode {
+        SaleMode::FixedPrice => None,
+        SaleMode::SealedBid { .. } | SaleMode::FairLaunch { .. } => state.clearing_price,
+        SaleMode::DutchAuction { floor_price, .. } => match state.clearing_price {
+            Some(price) => Some(price),
+            None if now >= sale_end_time(state) => Some(floor_price),
+            None => None,
+        },
+    }
 }
 
-pub fn execute_deposit_private_sale(deps: DepsMut, env: Env, info: MessageInfo, allo_info: AlloInfo, proof: Vec<String>) -> Result<Response, ContractError> {
-    let mut state: State = read_state(deps.storage)?;
-    let sender = info.sender.to_string();
+/// Fund value of `reward_amount` at `price` = fund value / reward * ACCURACY.
+fn calc_reward_value(state: &State, price: Uint128, reward_amount: Uint128, reward_decimals: u32) -> StdResult<Uint128> {
+    Ok(reward_amount.multiply_ratio(
+        price.checked_mul(Uint128::from(10u128).wrapping_pow(value_decimals(state)))?,
+        Uint128::from(ACCURACY).checked_mul(Uint128::from(10u128).wrapping_pow(reward_decimals))?,
+    ))
+}
 
-    /* Check if Presale in progress */
-    if env.block.time.seconds() < state.private_start_time {
-        return Err(ContractError::PrivateNotInProgress {  });
-    }
+pub fn calc_reward_amount(deps: Deps, state: &State, round: &Round, asset: &FundAsset, fund_amount: Uint128, oracle_price: Option<Decimal>) -> StdResult<Uint128> {
+    let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
 
-    /* Check fund tokens */
-    let amount ;
-    if let Some(coins) = info.funds.first() {
-        if coins.denom != state.fund_denom || coins.amount.is_zero() {
-            return Err(ContractError::Funds {  });
-        }
-        amount = coins.amount;
-    } else {
-        return Err(ContractError::Funds {  });
-    }
+    let value = calc_fund_value(state, asset, fund_amount, oracle_price)?;
+    let reward_amount = value.multiply_ratio(
+        Uint128::from(ACCURACY).checked_mul(Uint128::from(10u128).wrapping_pow(reward_decimals))?,
+        round_price(state, round).checked_mul(Uint128::from(10u128).wrapping_pow(value_decimals(state)))?,
+    );
 
-    /* Verify if whitelisted */
-    if state.whitelist_merkle_root.len() > 0 {
-        if verify_whitelist(state.clone(), &sender, &allo_info, &proof)? == false {
-            return Err(ContractError::NotWhitelisted {});
-        }
+    match &round.curve {
+        // curve never goes below the round price, so the flat price reward bounds the search
+        Some(curve) => curve_reward_amount(&curve_params(state, round, curve, reward_decimals), round.sold_amount, value, reward_amount),
+        None => Ok(reward_amount),
     }
+}
 
+pub fn calc_fund_amount(deps: Deps, state: &State, round: &Round, asset: &FundAsset, reward_amount: Uint128, oracle_price: Option<Decimal>) -> StdResult<Uint128> {
+    let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
 
-    let mut recp_info = Participant {
-        fund_balance: Uint128::zero(),
-        reward_balance: Uint128::zero()
+    let value = match &round.curve {
+        Some(curve) => curve_cost(&curve_params(state, round, curve, reward_decimals), round.sold_amount, reward_amount)?,
+        None => calc_reward_value(state, round_price(state, round), reward_amount, reward_decimals)?,
     };
-    let mut private_sold_fund = Uint128::zero();
+    calc_value_fund_amount(state, asset, value, oracle_price)
+}
 
-    /* Add to participants list */
-    if PARTICIPANTS.has(deps.storage, sender.clone()) {
-        recp_info = PARTICIPANTS.load(deps.storage, sender.clone())?;
-        private_sold_fund = PRIVATE_SOLD_FUNDS.load(deps.storage, sender.clone())?;
-    } else {
-        state.userlist.push(sender.clone());
+fn curve_params<'a>(state: &State, round: &Round, curve: &'a PricingCurve, reward_decimals: u32) -> CurveParams<'a> {
+    CurveParams {
+        curve,
+        base_price: round_price(state, round),
+        reward_decimals,
+        value_decimals: value_decimals(state),
     }
+}
 
-    /* Check allocation */
-    let new_fund_balance = recp_info.fund_balance + amount;
-    if allo_info.private_allocation < new_fund_balance {
-        return Err(ContractError::ExceedAllocation {  });
+/// Ratio converting an amount of the asset into fund value.
+/// Fund value is in units of the first fund asset, or USD with USD_DECIMALS when priced by the oracle.
+fn fund_value_ratio(state: &State, asset: &FundAsset, oracle_price: Option<Decimal>) -> StdResult<(Uint128, Uint128)> {
+    if let Some(price) = oracle_price {
+        return Ok((
+            Uint128::new(price.atomics().u128()).checked_mul(Uint128::from(10u128).wrapping_pow(USD_DECIMALS))?,
+            Uint128::new(Decimal::one().atomics().u128()).checked_mul(Uint128::from(10u128).wrapping_pow(asset.decimals))?,
+        ));
     }
 
-    /* Update rewards amount */
-    let reward_amount = calc_reward_amount(deps.as_ref(), state.clone(), amount)?;
-    recp_info.fund_balance = new_fund_balance;
-    recp_info.reward_balance = recp_info.reward_balance + reward_amount;
-    state.private_sold_amount = state.private_sold_amount + reward_amount;
-    private_sold_fund = private_sold_fund + amount;
+    let base = &state.fund_assets[0];
+    Ok((
+        base.exchange_rate.checked_mul(Uint128::from(10u128).wrapping_pow(base.decimals))?,
+        asset.exchange_rate.checked_mul(Uint128::from(10u128).wrapping_pow(asset.decimals))?,
+    ))
+}
 
-    store_state(deps.storage, &state)?;
-    PARTICIPANTS.save(deps.storage, sender.clone(), &recp_info)?;
-    PRIVATE_SOLD_FUNDS.save(deps.storage, sender.clone(), &private_sold_fund)?;
+/// Fund value of `amount` of the asset.
+/// Allocations, soft cap and participant fund balances are all measured in fund value.
+pub fn calc_fund_value(state: &State, asset: &FundAsset, amount: Uint128, oracle_price: Option<Decimal>) -> StdResult<Uint128> {
+    let (numerator, denominator) = fund_value_ratio(state, asset, oracle_price)?;
+    Ok(amount.multiply_ratio(numerator, denominator))
+}
 
-    /* Update vesting */
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
-        msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
-            recp: sender.clone(),
-            amount: recp_info.reward_balance.u128().try_into().unwrap(),
-        })?,
-        funds: vec![],
-    }));
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("method", "deposit_private"))
+/// Amount of the asset worth `value`, inverse of `calc_fund_value`.
+pub fn calc_value_fund_amount(state: &State, asset: &FundAsset, value: Uint128, oracle_price: Option<Decimal>) -> StdResult<Uint128> {
+    let (numerator, denominator) = fund_value_ratio(state, asset, oracle_price)?;
+    Ok(value.multiply_ratio(denominator, numerator))
 }
 
-pub fn execute_withdraw_funds(deps: DepsMut, env: Env, info: MessageInfo, receiver: String) -> Result<Response, ContractError> {
-    let state: State = read_state(deps.storage)?;
-    let receiver_addr = deps.api.addr_validate(&receiver)?;
+/// Current USD price of the asset when the sale is priced by the oracle.
+/// Rejects prices older than the configured max price age.
+pub fn query_oracle_price(deps: Deps, env: &Env, state: &State, asset: &FundAsset) -> Result<Option<Decimal>, ContractError> {
+    let oracle = match &state.oracle {
+        Some(oracle) => oracle,
+        None => return Ok(None),
+    };
 
-    // permission check
-    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
-        return Err(ContractError::Unauthorized {});
+    let res = query_price(deps, deps.api.addr_humanize(&oracle.contract)?, asset.info.to_string())?;
+    if res.price.is_zero() {
+        return Err(ContractError::InvalidPrice {  });
+    }
+    if res.last_updated.saturating_add(oracle.max_price_age) < env.block.time.seconds() {
+        return Err(ContractError::StalePrice {  });
+    }
+    Ok(Some(res.price))
+}
+
+/// Splits a deposit into the part that fits the asset cap, the allocation room, the round cap and the hard cap, and the excess to refund.
+/// Allocation room is given in fund value.
+/// Returns (accepted fund amount, accepted fund value, reward amount, refund amount).
+pub fn fill_deposit(deps: Deps, state: &State, round: &Round, asset: &FundAsset, amount: Uint128, allocation_room: Option<Uint128>, reserved_value: Uint128, oracle_price: Option<Decimal>) -> Result<(Uint128, Uint128, Uint128, Uint128), ContractError> {
+    let mut accepted = amount;
+    if !asset.cap.is_zero() {
+        let asset_room = asset.cap.saturating_sub(asset.private_raised + asset.public_raised);
+        if asset_room.is_zero() {
+            return Err(ContractError::AssetCapReached {  });
+        }
+        accepted = accepted.min(asset_room);
+    }
+    if let Some(room) = allocation_room {
+        if room.is_zero() {
+            return Err(ContractError::ExceedAllocation {  });
+        }
+        accepted = accepted.min(calc_value_fund_amount(state, asset, room, oracle_price)?);
     }
 
-    let end_time = state.public_start_time + state.presale_period;
-    if env.block.time.seconds() <= end_time {
-        return Err(ContractError::StillInProgress {  });
+    let mut remaining_rewards = state.total_rewards_amount
+        .saturating_sub(state.private_sold_amount + state.public_sold_amount);
+    if remaining_rewards.is_zero() {
+        return Err(ContractError::HardCapReached {  });
+    }
+    if !round.cap.is_zero() {
+        let round_room = round.cap.saturating_sub(round.sold_amount);
+        if round_room.is_zero() {
+            return Err(ContractError::RoundCapReached {  });
+        }
+        remaining_rewards = remaining_rewards.min(round_room);
+    }
+    // supply reserved for staking tier allocations of other stakers is not available
+    if !reserved_value.is_zero() {
+        let reserved_fund = calc_value_fund_amount(state, asset, reserved_value, oracle_price)?;
+        remaining_rewards = remaining_rewards.saturating_sub(calc_reward_amount(deps, state, round, asset, reserved_fund, oracle_price)?);
+        if remaining_rewards.is_zero() {
+            return Err(ContractError::SupplyReserved {  });
+        }
     }
 
-    let fund_balance = query_balance(deps.as_ref(), env.contract.address, state.fund_denom.clone())?;
+    let mut reward_amount = calc_reward_amount(deps, state, round, asset, accepted, oracle_price)?;
+    // on a curve only the cost of the bought rewards is charged
+    if reward_amount > remaining_rewards || round.curve.is_some() {
+        accepted = calc_fund_amount(deps, state, round, asset, reward_amount.min(remaining_rewards), oracle_price)?;
+        reward_amount = calc_reward_amount(deps, state, round, asset, accepted, oracle_price)?;
+    }
+    if accepted.is_zero() || reward_amount.is_zero() {
+        return Err(ContractError::HardCapReached {  });
+    }
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(CosmosMsg::Bank(BankMsg::Send {
-        to_address: receiver_addr.to_string(),
-        amount: vec![Coin {
-            denom: state.fund_denom.to_string(),
-            amount: fund_balance.clone(),
-        }]
-    }));
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("method", "withdraw_funds"))
+    let value = calc_fund_value(state, asset, accepted, oracle_price)?;
+    Ok((accepted, value, reward_amount, amount - accepted))
 }
 
-pub fn execute_withdraw_unsold_token(deps: DepsMut, env: Env, info: MessageInfo, receiver: String) -> Result<Response, ContractError> {
-    let state: State = read_state(deps.storage)?;
-    let receiver_addr = deps.api.addr_validate(&receiver)?;
+/// Fund value kept by an auction, the sold amount at the clearing price.
+fn auction_proceeds(deps: Deps, state: &State, price: Uint128) -> StdResult<Uint128> {
+    let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
+    calc_reward_value(state, price, state.private_sold_amount + state.public_sold_amount, reward_decimals)
+}
 
-    // permission check
-    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
-        return Err(ContractError::Unauthorized {});
+pub fn sale_outcome(deps: Deps, state: &State, now: u64) -> StdResult<SaleOutcome> {
+    if state.cancelled {
+        return Ok(SaleOutcome::Cancelled);
+    }
+    let ended = match state.sale_mode {
+        // escrowed sales end with settlement, the sold amount is unknown before
+        SaleMode::SealedBid { .. } | SaleMode::FairLaunch { .. } => state.fill_ratio.is_some(),
+        _ => now >= sale_end_time(state),
+    };
+    if !ended {
+        return Ok(SaleOutcome::Pending);
     }
 
-    let end_time = state.public_start_time + state.presale_period;
-    if env.block.time.seconds() <= end_time {
-        return Err(ContractError::StillInProgress {  });
+    // escrowed bids above the clearing price, unrevealed and losing bids are refunded, only the proceeds count
+    let raised = match state.sale_mode {
+        SaleMode::DutchAuction { .. } | SaleMode::SealedBid { .. } => match clearing_price(state, now) {
+            Some(price) => auction_proceeds(deps, state, price)?,
+            None => Uint128::zero(),
+        },
+        _ => state.total_raised,
+    };
+    if raised < state.soft_cap {
+        Ok(SaleOutcome::Failed)
+    } else {
+        Ok(SaleOutcome::Succeeded)
     }
+}
 
-    let reward_balance_info: BalanceResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-        contract_addr: deps.api.addr_humanize(&state.reward_token)?.to_string(),
-        msg: to_binary(&Cw20QueryMsg::Balance {
-            address: deps.api.addr_humanize(&state.vesting)?.to_string()
-        })?,
-    }))?;
+/// Bond amount counted for the staking tier and the index of the tier reached.
+/// Registered snapshot is capped by the current bond, so unstaking before the deposit lowers the tier.
+fn staking_tier(deps: Deps, staking: &StakingTiers, user: &str) -> StdResult<(Uint128, Option<usize>)> {
+    let snapshot_bond = STAKE_SNAPSHOTS.may_load(deps.storage, user.to_string())?.unwrap_or_default();
+    if snapshot_bond.is_zero() {
+        return Ok((Uint128::zero(), None));
+    }
 
-    let sold_amount = state.private_sold_amount + state.public_sold_amount;
-    let unsold_amount = reward_balance_info.balance - Uint128::from(sold_amount);
+    let bond_amount = snapshot_bond.min(query_bond_amount(deps, deps.api.addr_humanize(&staking.contract)?, user.to_string())?);
+    let tier = staking.tiers.iter().rposition(|tier| tier.min_bond <= bond_amount);
+    Ok((bond_amount, tier))
+}
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: deps.api.addr_humanize(&state.reward_token)?.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
-            owner: deps.api.addr_humanize(&state.vesting)?.to_string(),
-            recipient: receiver_addr.to_string(),
-            amount: unsold_amount,
-        })?,
-        funds: vec![],
-    }));
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("method", "withdraw_unsold_token"))
+/// Guaranteed private allocation of the staking tier in fund value, reserved on registration.
+fn tier_allocation(staking: &StakingTiers, tier: Option<usize>) -> Uint128 {
+    match tier {
+        Some(index) => staking.base_allocation * staking.tiers[index].multiplier,
+        None => Uint128::zero(),
+    }
 }
 
-/************************************ Query *************************************/
+/// Boosted private allocation of the lock position in fund value, with the locked amount and lock age.
+/// Positions being unlocked are not eligible, so starting an unlock during the sale drops the boost.
+fn lock_allocation(deps: Deps, locking: &LockingWeights, user: &str, now: u64) -> StdResult<(Uint128, u64, Option<Uint128>)> {
+    let lock_info = query_lock_info(deps, deps.api.addr_humanize(&locking.contract)?, user.to_string())?;
+    // positions locked before lock times were tracked have no age
+    let lock_age = if lock_info.locked_at == 0 { 0 } else { now.saturating_sub(lock_info.locked_at) };
+    if lock_info.amount.is_zero() || lock_info.unlock_started != 0 || lock_age < locking.min_lock_age {
+        return Ok((lock_info.amount, lock_age, None));
+    }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::ParticipantsCount {} => to_binary(&query_count(deps)?),
-        QueryMsg::GetParticipants { page, limit } => to_binary(&query_participants(deps, page, limit)?),
-        QueryMsg::GetParticipant { user } => to_binary(&query_participant(deps, user)?),
-        QueryMsg::GetSaleStatus { } => to_binary( &query_sale_status(deps)? ),
-        QueryMsg::PresaleInfo { } => to_binary( &query_presale_info(deps)? )
+    let boost = if lock_age >= locking.boost_period {
+        locking.max_boost
+    } else {
+        Decimal::one() + (locking.max_boost - Decimal::one()) * Decimal::from_ratio(lock_age, locking.boost_period)
+    };
+    Ok((lock_info.amount, lock_age, Some(lock_info.amount * locking.allocation_rate * boost)))
+}
+
+/// Private allocation from the staking tier or the lock position, whichever is higher.
+/// Only staking tier allocations reserve supply, lock positions raise the wallet limit.
+fn staking_allocation(deps: Deps, state: &State, user: &str, now: u64) -> StdResult<Uint128> {
+    let mut allocation = Uint128::zero();
+    if let Some(staking) = &state.staking {
+        allocation = tier_allocation(staking, staking_tier(deps, staking, user)?.1);
     }
+    if let Some(locking) = &state.locking {
+        allocation = allocation.max(lock_allocation(deps, locking, user, now)?.2.unwrap_or_default());
+    }
+    Ok(allocation)
 }
 
-fn query_count(deps: Deps) -> StdResult<ParticipantsCountResponse> {
-    let state: State = read_state(deps.storage)?;
-    Ok(ParticipantsCountResponse { count: state.userlist.len() as u64 })
+/// Fund value still reserved for the staker, the registered tier allocation less private purchases.
+fn outstanding_reservation(storage: &dyn Storage, user: &str, private_sold_fund: Uint128) -> StdResult<Uint128> {
+    Ok(TIER_RESERVATIONS.may_load(storage, user.to_string())?.unwrap_or_default().saturating_sub(private_sold_fund))
+}
+
+/// Tier allocations are reserved until the last private round ends.
+fn active_reservations(state: &State, now: u64) -> Uint128 {
+    if state.rounds.iter().any(|round| round.kind == RoundKind::Private && now < round.end_time) {
+        state.reserved_allocations
+    } else {
+        Uint128::zero()
+    }
+}
+
+pub fn execute_register_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let staking = state.staking.clone().ok_or(ContractError::InvalidInput {  })?;
+
+    if env.block.time.seconds() >= staking.snapshot_time {
+        return Err(ContractError::SnapshotPassed {  });
+    }
+
+    let bond_amount = query_bond_amount(deps.as_ref(), deps.api.addr_humanize(&staking.contract)?, info.sender.to_string())?;
+    STAKE_SNAPSHOTS.save(deps.storage, info.sender.to_string(), &bond_amount)?;
+
+    /* Reserve the tier allocation, registering again replaces the reservation */
+    let tier = staking.tiers.iter().rposition(|tier| tier.min_bond <= bond_amount);
+    let reservation = tier_allocation(&staking, tier);
+    let previous = TIER_RESERVATIONS.may_load(deps.storage, info.sender.to_string())?.unwrap_or_default();
+    TIER_RESERVATIONS.save(deps.storage, info.sender.to_string(), &reservation)?;
+    state.reserved_allocations = state.reserved_allocations - previous + reservation;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_stake"),
+        attr("staker", info.sender.to_string()),
+        attr("bond_amount", bond_amount.to_string()),
+    ]))
+}
+
+// Domain tag of whitelist permits.
+const PERMIT_DOMAIN: &[u8] = b"presale/permit/v1";
+
+/// Message hash signed by the whitelist signer, encoded like whitelist leaves.
+pub fn permit_hash(contract: &str, round_id: u64, sender: &str, allo_info: &AlloInfo, expiry: u64, nonce: u64) -> [u8; 32] {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(length_prefixed(PERMIT_DOMAIN));
+    hasher.update(length_prefixed(contract.as_bytes()));
+    hasher.update(round_id.to_be_bytes());
+    hasher.update(length_prefixed(sender.as_bytes()));
+    hasher.update(allo_info.private_allocation.u128().to_be_bytes());
+    hasher.update(allo_info.public_allocation.u128().to_be_bytes());
+    hasher.update(expiry.to_be_bytes());
+    hasher.update(nonce.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Checks the permit signed by the whitelist signer for the sender's allocation in the round.
+fn verify_permit(deps: Deps, env: &Env, state: &State, round_id: u64, sender: &str, allo_info: &AlloInfo, permit: &WhitelistPermit) -> Result<(), ContractError> {
+    let signer = state.whitelist_signer.as_ref().ok_or(ContractError::InvalidInput {  })?;
+
+    if env.block.time.seconds() >= permit.expiry {
+        return Err(ContractError::PermitExpired {  });
+    }
+    if USED_PERMITS.has(deps.storage, (sender.to_string(), permit.nonce)) {
+        return Err(ContractError::PermitUsed {  });
+    }
+
+    let hash = permit_hash(env.contract.address.as_str(), round_id, sender, allo_info, permit.expiry, permit.nonce);
+    let verified = match signer.scheme {
+        SignatureScheme::Secp256k1 => deps.api.secp256k1_verify(&hash, &permit.signature, &signer.public_key),
+        SignatureScheme::Ed25519 => deps.api.ed25519_verify(&hash, &permit.signature, &signer.public_key),
+    };
+    if !verified.unwrap_or(false) {
+        return Err(ContractError::VerificationFailed {  });
+    }
+    Ok(())
+}
+
+pub fn execute_set_whitelist_signer(deps: DepsMut, info: MessageInfo, signer: Option<WhitelistSigner>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(signer) = &signer {
+        validate_signer(signer)?;
+    }
+    state.whitelist_signer = signer;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_whitelist_signer"),
+    ]))
+}
+
+fn validate_signer(signer: &WhitelistSigner) -> Result<(), ContractError> {
+    let valid = match signer.scheme {
+        SignatureScheme::Secp256k1 => matches!(signer.public_key.len(), 33 | 65),
+        SignatureScheme::Ed25519 => signer.public_key.len() == 32,
+    };
+    if !valid {
+        return Err(ContractError::InvalidInput {  });
+    }
+    Ok(())
+}
+
+fn assert_not_paused(state: &State) -> Result<(), ContractError> {
+    if state.paused_at.is_some() {
+        return Err(ContractError::Paused {  });
+    }
+    Ok(())
+}
+
+pub fn execute_set_guardian(deps: DepsMut, info: MessageInfo, guardian: Option<String>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.guardian = match guardian {
+        Some(guardian) => Some(deps.api.addr_canonicalize(&guardian)?),
+        None => None,
+    };
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_guardian"),
+    ]))
+}
+
+pub fn execute_pause(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if Some(deps.api.addr_canonicalize(info.sender.as_str())?) != state.guardian {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    assert_not_paused(&state)?;
+    state.paused_at = Some(env.block.time.seconds());
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "pause"),
+    ]))
+}
+
+pub fn execute_unpause(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if Some(deps.api.addr_canonicalize(info.sender.as_str())?) != state.guardian {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let paused_at = state.paused_at.take().ok_or(ContractError::InvalidInput {  })?;
+    let paused_duration = env.block.time.seconds() - paused_at;
+
+    /* Rounds running or upcoming when paused are moved by the paused duration */
+    if state.extend_on_pause {
+        for round in state.rounds.iter_mut().filter(|round| round.end_time > paused_at) {
+            if round.start_time >= paused_at {
+                round.start_time += paused_duration;
+            }
+            round.end_time += paused_duration;
+        }
+    }
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "unpause"),
+        attr("paused_duration", paused_duration.to_string()),
+    ]))
+}
+
+fn assert_not_blocked(storage: &dyn Storage, address: &str) -> Result<(), ContractError> {
+    if BLOCKLIST.has(storage, address.to_string()) {
+        return Err(ContractError::Blocked {  });
+    }
+    Ok(())
+}
+
+pub fn execute_set_compliance(deps: DepsMut, info: MessageInfo, compliance: Option<String>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.compliance = match compliance {
+        Some(compliance) => Some(deps.api.addr_canonicalize(&compliance)?),
+        None => None,
+    };
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_compliance"),
+    ]))
+}
+
+fn assert_compliance(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    // permission check
+    let sender = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender != state.owner && Some(sender) != state.compliance {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn blocklist_event(action: &str, operator: &str, address: &str, reason: u32) -> Event {
+    Event::new("blocklist")
+        .add_attribute("action", action)
+        .add_attribute("operator", operator)
+        .add_attribute("address", address)
+        .add_attribute("reason", reason.to_string())
+}
+
+pub fn execute_block(deps: DepsMut, env: Env, info: MessageInfo, address: String, reason: u32) -> Result<Response, ContractError> {
+    assert_compliance(deps.as_ref(), &info)?;
+
+    BLOCKLIST.save(deps.storage, address.clone(), &BlockInfo { reason, blocked_at: env.block.time.seconds() })?;
+
+    Ok(Response::new()
+        .add_event(blocklist_event("block", info.sender.as_str(), &address, reason))
+        .add_attributes(vec![
+            attr("action", "block"),
+        ]))
+}
+
+pub fn execute_unblock(deps: DepsMut, info: MessageInfo, address: String, reason: u32) -> Result<Response, ContractError> {
+    assert_compliance(deps.as_ref(), &info)?;
+
+    if !BLOCKLIST.has(deps.storage, address.clone()) {
+        return Err(ContractError::InvalidInput {  });
+    }
+    BLOCKLIST.remove(deps.storage, address.clone());
+
+    Ok(Response::new()
+        .add_event(blocklist_event("unblock", info.sender.as_str(), &address, reason))
+        .add_attributes(vec![
+            attr("action", "unblock"),
+        ]))
+}
+
+fn attestation_gate(deps: Deps, attestation: AttestationInfo) -> Result<AttestationGate, ContractError> {
+    if attestation.blocked_jurisdictions.iter().any(|code| code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic())) {
+        return Err(ContractError::InvalidInput {  });
+    }
+    Ok(AttestationGate {
+        registry: deps.api.addr_canonicalize(&attestation.registry)?,
+        blocked_jurisdictions: attestation.blocked_jurisdictions.iter().map(|code| code.to_ascii_uppercase()).collect(),
+    })
+}
+
+/// Requires a valid attestation of the sender outside the blocked jurisdictions.
+fn assert_attested(deps: Deps, state: &State, sender: &str) -> Result<(), ContractError> {
+    let gate = match &state.attestation {
+        Some(gate) => gate,
+        None => return Ok(()),
+    };
+
+    let res = query_attestation(deps, deps.api.addr_humanize(&gate.registry)?, sender.to_string())?;
+    let attestation = match res.attestation {
+        Some(attestation) if res.valid => attestation,
+        _ => return Err(ContractError::NotAttested {  }),
+    };
+    if gate.blocked_jurisdictions.contains(&attestation.jurisdiction) {
+        return Err(ContractError::BlockedJurisdiction {  });
+    }
+    Ok(())
+}
+
+pub fn execute_set_attestation(deps: DepsMut, info: MessageInfo, attestation: Option<AttestationInfo>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.attestation = match attestation {
+        Some(attestation) => Some(attestation_gate(deps.as_ref(), attestation)?),
+        None => None,
+    };
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_attestation"),
+    ]))
+}
+
+// Max tokens claimed by one message.
+const MAX_CLAIMED_TOKENS: u32 = 30;
+
+pub fn execute_claim_token_allocation(deps: DepsMut, env: Env, info: MessageInfo, token_ids: Option<Vec<String>>) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+    let nft_gate = state.nft_gate.as_ref().ok_or(ContractError::InvalidInput {  })?;
+
+    if env.block.time.seconds() >= sale_end_time(&state) {
+        return Err(ContractError::RoundNotInProgress {  });
+    }
+
+    let collection = deps.api.addr_humanize(&nft_gate.collection)?;
+    let sender = info.sender.to_string();
+
+    /* Listed tokens must be owned and unused, otherwise unused owned tokens are picked */
+    let token_ids = match token_ids {
+        Some(token_ids) => {
+            if token_ids.len() > MAX_CLAIMED_TOKENS as usize {
+                return Err(ContractError::InvalidInput {  });
+            }
+            for token_id in &token_ids {
+                if query_nft_owner(deps.as_ref(), collection.clone(), token_id.clone())? != sender {
+                    return Err(ContractError::NotTokenOwner {  });
+                }
+                if USED_TOKENS.has(deps.storage, token_id.clone()) {
+                    return Err(ContractError::TokenUsed {  });
+                }
+            }
+            token_ids
+        },
+        None => query_nft_tokens(deps.as_ref(), collection, sender.clone(), Some(MAX_CLAIMED_TOKENS))?
+            .into_iter()
+            .filter(|token_id| !USED_TOKENS.has(deps.storage, token_id.clone()))
+            .collect(),
+    };
+    if token_ids.is_empty() {
+        return Err(ContractError::NoTokenAllocation {  });
+    }
+
+    for token_id in &token_ids {
+        USED_TOKENS.save(deps.storage, token_id.clone(), &sender)?;
+    }
+    let claimed = nft_gate.allocation_per_token * Uint128::from(token_ids.len() as u128);
+    let allocation = TOKEN_ALLOCATIONS.may_load(deps.storage, sender.clone())?.unwrap_or_default() + claimed;
+    TOKEN_ALLOCATIONS.save(deps.storage, sender.clone(), &allocation)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "claim_token_allocation"),
+        attr("holder", sender),
+        attr("token_ids", token_ids.join(",")),
+        attr("allocation", allocation.to_string()),
+    ]))
+}
+
+/// Tickets backed by the current balance of the ticket source, positions being unlocked count none.
+fn lottery_tickets(deps: Deps, lottery: &Lottery, user: &str) -> StdResult<Uint128> {
+    let balance = match &lottery.ticket_source {
+        TicketSource::Staking { contract } => query_bond_amount(deps, deps.api.addr_validate(contract)?, user.to_string())?,
+        TicketSource::Locking { contract } => {
+            let lock_info = query_lock_info(deps, deps.api.addr_validate(contract)?, user.to_string())?;
+            if lock_info.unlock_started != 0 { Uint128::zero() } else { lock_info.amount }
+        },
+    };
+    Ok(balance / lottery.ticket_size)
+}
+
+pub fn execute_register_lottery(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let mut lottery = state.lottery.ok_or(ContractError::InvalidInput {  })?;
+
+    if env.block.time.seconds() >= lottery.registration_end {
+        return Err(ContractError::RegistrationClosed {  });
+    }
+    if LOTTERY_TICKETS.has(deps.storage, info.sender.to_string()) {
+        return Err(ContractError::AlreadyRegistered {  });
+    }
+    // winners are drawn over all entrants in one transaction
+    if lottery.entrants >= MAX_LOTTERY_ENTRANTS {
+        return Err(ContractError::LotteryFull {  });
+    }
+
+    /* Count tickets from the source balance */
+    let tickets = lottery_tickets(deps.as_ref(), &lottery, info.sender.as_str())?;
+    if tickets.is_zero() {
+        return Err(ContractError::NoTickets {  });
+    }
+
+    LOTTERY_TICKETS.save(deps.storage, info.sender.to_string(), &tickets)?;
+    lottery.total_tickets += tickets;
+    lottery.entrants += 1;
+    state.lottery = Some(lottery);
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_lottery"),
+        attr("entrant", info.sender.to_string()),
+        attr("tickets", tickets.to_string()),
+    ]))
+}
+
+pub fn execute_submit_beacon(deps: DepsMut, round: u64, previous_signature: String, signature: String) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let mut lottery = state.lottery.ok_or(ContractError::InvalidInput {  })?;
+
+    if lottery.randomness.is_some() {
+        return Err(ContractError::AlreadyDrawn {  });
+    }
+
+    /* Beacon must be the first one emitted after the registration closed */
+    let expected_round = lottery.registration_end
+        .checked_sub(lottery.drand_genesis_time)
+        .and_then(|elapsed| (elapsed / lottery.drand_period).checked_add(2))
+        .ok_or(ContractError::InvalidBeacon {  })?;
+    if round != expected_round {
+        return Err(ContractError::InvalidBeacon {  });
+    }
+
+    /* Verify beacon against the pinned drand public key */
+    let signature = hex::decode(&signature)?;
+    if !verify_beacon(&hex::decode(&lottery.drand_public_key)?, round, &hex::decode(&previous_signature)?, &signature) {
+        return Err(ContractError::InvalidBeacon {  });
+    }
+    let randomness = beacon_randomness(&signature);
+
+    /* Draw winners */
+    let entrants = LOTTERY_TICKETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Uint128)>>>()?;
+    let winners = draw_winners(&randomness, entrants, lottery.winners);
+    for winner in winners.iter() {
+        LOTTERY_WINNERS.save(deps.storage, winner.clone(), &true)?;
+    }
+
+    lottery.beacon_round = Some(round);
+    lottery.randomness = Some(hex::encode(&randomness));
+    state.lottery = Some(lottery);
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "submit_beacon"),
+        attr("round", round.to_string()),
+        attr("randomness", hex::encode(&randomness)),
+        attr("winners", winners.len().to_string()),
+    ]))
+}
+
+// Domain tag of whitelist leaves, versioned with the leaf encoding.
+const WHITELIST_DOMAIN: &[u8] = b"presale/whitelist/v2";
+// Leaves and inner nodes are hashed with distinct prefixes, so an inner node can not be presented as a leaf.
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+fn length_prefixed(bytes: &[u8]) -> Vec<u8> {
+    [&(bytes.len() as u32).to_be_bytes()[..], bytes].concat()
+}
+
+/// Whitelist leaf bound to the presale contract and the round.
+/// Strings are length-prefixed and numbers big-endian, so different fields can not encode the same bytes.
+pub fn whitelist_leaf(contract: &str, round_id: u64, sender: &str, allo_info: &AlloInfo) -> [u8; 32] {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(LEAF_PREFIX);
+    hasher.update(length_prefixed(WHITELIST_DOMAIN));
+    hasher.update(length_prefixed(contract.as_bytes()));
+    hasher.update(round_id.to_be_bytes());
+    hasher.update(length_prefixed(sender.as_bytes()));
+    hasher.update(allo_info.private_allocation.u128().to_be_bytes());
+    hasher.update(allo_info.public_allocation.u128().to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Leaf of the legacy format, sender and allocations concatenated as strings.
+fn legacy_whitelist_leaf(sender: &str, allo_info: &AlloInfo) -> [u8; 32] {
+    let user_input = format!("{}{}{}", sender, allo_info.private_allocation, allo_info.public_allocation);
+    sha2::Sha256::digest(user_input.as_bytes()).into()
+}
+
+/// Hashes the leaf up the proof, sibling pairs are sorted before hashing.
+fn fold_proof(leaf: [u8; 32], proof: &[String], node_prefix: &[u8]) -> Result<[u8; 32], ContractError> {
+    proof.iter().try_fold(leaf, |hash, p| {
+        let mut proof_buf = [0; 32];
+        hex::decode_to_slice(p, &mut proof_buf)?;
+        let mut hashes = [hash, proof_buf];
+        hashes.sort_unstable();
+        Ok(sha2::Sha256::digest([node_prefix, &hashes.concat()].concat()).into())
+    })
+}
+
+/// Verifies the whitelist proof of the sender for the round.
+/// Legacy leaves and unprefixed inner nodes are accepted while `accept_legacy` is set.
+pub fn verify_whitelist(merkle_root: &str, contract: &str, round_id: u64, sender: &str, allo_info: &AlloInfo, proof: &[String], accept_legacy: bool) -> Result<bool, ContractError> {
+    let mut root_buf: [u8; 32] = [0; 32];
+    hex::decode_to_slice(merkle_root, &mut root_buf)?;
+
+    if fold_proof(whitelist_leaf(contract, round_id, sender, allo_info), proof, NODE_PREFIX)? == root_buf {
+        return Ok(true);
+    }
+    Ok(accept_legacy && fold_proof(legacy_whitelist_leaf(sender, allo_info), proof, &[])? == root_buf)
+}
+
+pub fn execute_deposit(deps: DepsMut, env: Env, sender: String, asset_info: AssetInfo, amount: Uint128, allo_info: AlloInfo, proof: Vec<String>, permit: Option<WhitelistPermit>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let asset_index = fund_asset_index(&state, &asset_info)?;
+
+    if let SaleMode::SealedBid { .. } = state.sale_mode {
+        return Err(ContractError::UnsupportedSaleMode {  });
+    }
+    if state.cancelled {
+        return Err(ContractError::SaleCancelled {  });
+    }
+    if state.escrowed_rewards < state.total_rewards_amount {
+        return Err(ContractError::RewardsNotFunded {  });
+    }
+
+    /* Resolve the active round */
+    let round_index = active_round_index(&state, env.block.time.seconds())
+        .ok_or(ContractError::RoundNotInProgress {  })?;
+    let round = deposit_round(&state, round_index, env.block.time.seconds());
+
+    assert_not_paused(&state)?;
+    assert_not_blocked(deps.storage, &sender)?;
+    assert_attested(deps.as_ref(), &state, &sender)?;
+
+    /* Only lottery winners may deposit */
+    if let Some(lottery) = &state.lottery {
+        if lottery.randomness.is_none() {
+            return Err(ContractError::LotteryNotDrawn {  });
+        }
+        if !LOTTERY_WINNERS.has(deps.storage, sender.clone()) {
+            return Err(ContractError::NotWinner {  });
+        }
+        // like staking tiers, the registered tickets must still be backed, a stake moved to another entrant can't win twice
+        let tickets = LOTTERY_TICKETS.may_load(deps.storage, sender.clone())?.unwrap_or_default();
+        if lottery_tickets(deps.as_ref(), lottery, &sender)? < tickets {
+            return Err(ContractError::TicketsNotBacked {  });
+        }
+    }
+
+    let mut recp_info = Participant {
+        fund_balance: Uint128::zero(),
+        reward_balance: Uint128::zero(),
+        claimed: false,
+        registered: false,
+        frozen: false,
+    };
+    let mut private_sold_fund = Uint128::zero();
+
+    /* Add to participants list */
+    if PARTICIPANTS.has(deps.storage, sender.clone()) {
+        recp_info = PARTICIPANTS.load(deps.storage, sender.clone())?;
+        private_sold_fund = PRIVATE_SOLD_FUNDS.may_load(deps.storage, sender.clone())?.unwrap_or_default();
+    } else {
+        state.userlist.push(sender.clone());
+    }
+
+    /* On-chain whitelist replaces the claimed allocation */
+    let listed = match state.whitelist_mode {
+        WhitelistMode::OnChain => Some(WHITELIST.may_load(deps.storage, sender.clone())?),
+        WhitelistMode::Merkle => None,
+    };
+    let allo_info = match &listed {
+        Some(entry) => entry.clone().unwrap_or(AlloInfo { private_allocation: Uint128::zero(), public_allocation: Uint128::zero() }),
+        None => allo_info,
+    };
+
+    /* Signed permit replaces the merkle proof */
+    let permitted = match permit.filter(|_| listed.is_none()) {
+        Some(permit) => {
+            verify_permit(deps.as_ref(), &env, &state, round.id, &sender, &allo_info, &permit)?;
+            USED_PERMITS.save(deps.storage, (sender.clone(), permit.nonce), &true)?;
+            true
+        },
+        None => false,
+    };
+
+    let mut allocation_room = None;
+    let accept_legacy = env.block.time.seconds() < state.legacy_whitelist_until;
+    match round.kind {
+        RoundKind::Private => {
+            /* Staking tier allocations are guaranteed, lock positions raise the wallet limit */
+            let staking_limit = staking_allocation(deps.as_ref(), &state, &sender, env.block.time.seconds())?;
+
+            /* Verify if whitelisted, stakers and lockers need no proof */
+            let merkle_root = round.merkle_root.clone().unwrap_or_else(|| state.whitelist_merkle_root.clone());
+            let whitelisted = match &listed {
+                Some(entry) => entry.is_some(),
+                None => permitted || merkle_root.is_empty() || verify_whitelist(&merkle_root, env.contract.address.as_str(), round.id, &sender, &allo_info, &proof, accept_legacy)?,
+            };
+            if !whitelisted && staking_limit.is_zero() {
+                return Err(ContractError::NotWhitelisted {});
+            }
+
+            let allocation = if whitelisted { allo_info.private_allocation.max(staking_limit) } else { staking_limit };
+            allocation_room = Some(allocation.saturating_sub(private_sold_fund));
+        },
+        RoundKind::Public => {
+            let merkle_root = match &state.public_whitelist {
+                PublicWhitelist::Open => None,
+                PublicWhitelist::SharedRoot => Some(round.merkle_root.clone().unwrap_or_else(|| state.whitelist_merkle_root.clone())),
+                PublicWhitelist::SeparateRoot { merkle_root } => Some(merkle_root.clone()),
+            };
+
+            if let Some(merkle_root) = merkle_root {
+                /* Verify if whitelisted */
+                let whitelisted = match &listed {
+                    Some(entry) => entry.is_some(),
+                    None => permitted || merkle_root.is_empty() || verify_whitelist(&merkle_root, env.contract.address.as_str(), round.id, &sender, &allo_info, &proof, accept_legacy)?,
+                };
+                if !whitelisted {
+                    return Err(ContractError::NotWhitelisted {});
+                }
+
+                /* Check allocation, private purchases are not counted against the public allocation */
+                allocation_room = Some((allo_info.public_allocation + private_sold_fund).saturating_sub(recp_info.fund_balance));
+            }
+        },
+    }
+
+    /* Check per-wallet cap of the round */
+    let round_fund = ROUND_FUNDS.may_load(deps.storage, (round.id, sender.clone()))?.unwrap_or_default();
+    if !round.wallet_cap.is_zero() {
+        let wallet_room = round.wallet_cap.saturating_sub(round_fund);
+        allocation_room = Some(allocation_room.map_or(wallet_room, |room: Uint128| room.min(wallet_room)));
+    }
+
+    /* Check allocation claimed by held tokens */
+    if state.nft_gate.is_some() {
+        let token_allocation = TOKEN_ALLOCATIONS.may_load(deps.storage, sender.clone())?.unwrap_or_default();
+        if token_allocation.is_zero() {
+            return Err(ContractError::NoTokenAllocation {  });
+        }
+        let token_room = token_allocation.saturating_sub(recp_info.fund_balance);
+        allocation_room = Some(allocation_room.map_or(token_room, |room: Uint128| room.min(token_room)));
+    }
+
+    /* Check lottery allocation of the winner */
+    if let Some(lottery) = &state.lottery {
+        if !lottery.allocation.is_zero() {
+            let lottery_room = lottery.allocation.saturating_sub(recp_info.fund_balance);
+            allocation_room = Some(allocation_room.map_or(lottery_room, |room: Uint128| room.min(lottery_room)));
+        }
+    }
+
+    /* Supply reserved for other stakers stays available to them, private purchases use the own reservation */
+    let own_reservation = outstanding_reservation(deps.storage, &sender, private_sold_fund)?;
+    let mut reserved_value = active_reservations(&state, env.block.time.seconds());
+    if round.kind == RoundKind::Private {
+        reserved_value = reserved_value.saturating_sub(own_reservation);
+    }
+
+    /* Check allocation and hard cap, excess funds are refunded */
+    let oracle_price = query_oracle_price(deps.as_ref(), &env, &state, &state.fund_assets[asset_index])?;
+    let (accepted, value, reward_amount, refund) = match state.sale_mode {
+        // fair launch escrows any amount, rewards are computed pro rata on claim
+        SaleMode::FairLaunch { .. } => (amount, calc_fund_value(&state, &state.fund_assets[asset_index], amount, oracle_price)?, Uint128::zero(), Uint128::zero()),
+        _ => fill_deposit(deps.as_ref(), &state, &round, &state.fund_assets[asset_index], amount, allocation_room, reserved_value, oracle_price)?,
+    };
+
+    /* Update rewards amount */
+    recp_info.fund_balance += value;
+    recp_info.reward_balance += reward_amount;
+    state.total_raised += value;
+    state.rounds[round_index].sold_amount += reward_amount;
+    state.rounds[round_index].raised += value;
+    let method = match round.kind {
+        RoundKind::Private => {
+            state.private_sold_amount += reward_amount;
+            state.fund_assets[asset_index].private_raised += accepted;
+            private_sold_fund += value;
+            PRIVATE_SOLD_FUNDS.save(deps.storage, sender.clone(), &private_sold_fund)?;
+            state.reserved_allocations -= own_reservation - outstanding_reservation(deps.storage, &sender, private_sold_fund)?;
+            "deposit_private"
+        },
+        RoundKind::Public => {
+            state.public_sold_amount += reward_amount;
+            state.fund_assets[asset_index].public_raised += accepted;
+            "deposit"
+        },
+    };
+
+    /* Auction ends at the price the supply sold out */
+    if matches!(state.sale_mode, SaleMode::DutchAuction { .. }) && state.private_sold_amount + state.public_sold_amount >= state.total_rewards_amount {
+        state.clearing_price = round.exchange_rate;
+    }
+
+    store_state(deps.storage, &state)?;
+    PARTICIPANTS.save(deps.storage, sender.clone(), &recp_info)?;
+    ROUND_FUNDS.save(deps.storage, (round.id, sender.clone()), &(round_fund + value))?;
+    ROUND_REWARDS.update(deps.storage, (round.id, sender.clone()), |rewards| -> StdResult<_> {
+        Ok(rewards.unwrap_or_default() + reward_amount)
+    })?;
+    PARTICIPANT_FUNDS.update(deps.storage, (sender.clone(), asset_info.to_string()), |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default() + accepted)
+    })?;
+
+    /* Update vesting, escrowed bids are registered on claim */
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if state.sale_mode == SaleMode::FixedPrice {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
+            msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
+                recp: sender.clone(),
+                amount: recp_info.reward_balance.u128().try_into().unwrap(),
+            })?,
+            funds: vec![],
+        }));
+    }
+    if !refund.is_zero() {
+        messages.push(fund_transfer_msg(&asset_info, sender, refund)?);
+    }
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("method", method),
+            attr("round_id", round.id.to_string()),
+            attr("amount", accepted.to_string()),
+            attr("refund", refund.to_string()),
+        ]))
+}
+
+pub fn execute_deposit_private_sale(deps: DepsMut, env: Env, sender: String, asset_info: AssetInfo, amount: Uint128, allo_info: AlloInfo, proof: Vec<String>, permit: Option<WhitelistPermit>) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    /* Check if private round in progress */
+    match active_round_index(&state, env.block.time.seconds()) {
+        Some(index) if state.rounds[index].kind == RoundKind::Private => {},
+        _ => return Err(ContractError::PrivateNotInProgress {  }),
+    }
+
+    execute_deposit(deps, env, sender, asset_info, amount, allo_info, proof, permit)
+}
+
+fn validate_withdrawal_penalty(deps: Deps, withdrawal_penalty: WithdrawalPenaltyInfo) -> Result<WithdrawalPenalty, ContractError> {
+    if withdrawal_penalty.start_rate > Decimal::one() || withdrawal_penalty.end_rate > Decimal::one() {
+        return Err(ContractError::InvalidInput {  });
+    }
+    Ok(WithdrawalPenalty {
+        treasury: deps.api.addr_canonicalize(&withdrawal_penalty.treasury)?,
+        start_rate: withdrawal_penalty.start_rate,
+        end_rate: withdrawal_penalty.end_rate,
+    })
+}
+
+/// Penalty rate moving linearly from the start rate at the sale start to the end rate at the sale end.
+pub fn withdrawal_penalty_rate(state: &State, penalty: &WithdrawalPenalty, now: u64) -> Decimal {
+    let start_time = state.rounds[0].start_time;
+    let end_time = sale_end_time(state);
+    if now <= start_time {
+        return penalty.start_rate;
+    }
+    if now >= end_time {
+        return penalty.end_rate;
+    }
+    let progress = Decimal::from_ratio(now - start_time, end_time - start_time);
+    if penalty.start_rate >= penalty.end_rate {
+        penalty.start_rate - (penalty.start_rate - penalty.end_rate) * progress
+    } else {
+        penalty.start_rate + (penalty.end_rate - penalty.start_rate) * progress
+    }
+}
+
+pub fn execute_set_withdrawal_penalty(deps: DepsMut, info: MessageInfo, withdrawal_penalty: Option<WithdrawalPenaltyInfo>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.withdrawal_penalty = match withdrawal_penalty {
+        Some(withdrawal_penalty) => Some(validate_withdrawal_penalty(deps.as_ref(), withdrawal_penalty)?),
+        None => None,
+    };
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_withdrawal_penalty"),
+    ]))
+}
+
+pub fn execute_withdraw_contribution(deps: DepsMut, env: Env, info: MessageInfo, amount: Uint128) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let sender = info.sender.to_string();
+
+    let penalty = state.withdrawal_penalty.clone()
+        .ok_or(ContractError::WithdrawalDisabled {  })?;
+    match state.sale_mode {
+        SaleMode::FixedPrice | SaleMode::FairLaunch { .. } => {},
+        // bids are not withdrawn, they set the clearing price
+        _ => return Err(ContractError::UnsupportedSaleMode {  }),
+    }
+    if state.cancelled {
+        return Err(ContractError::SaleCancelled {  });
+    }
+    if env.block.time.seconds() >= sale_end_time(&state) {
+        return Err(ContractError::SaleEnded {  });
+    }
+    assert_not_paused(&state)?;
+    assert_not_blocked(deps.storage, &sender)?;
+
+    let mut recp_info = PARTICIPANTS.may_load(deps.storage, sender.clone())?
+        .ok_or(ContractError::NothingToRefund {  })?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidInput {  });
+    }
+    if amount > recp_info.fund_balance {
+        return Err(ContractError::ExceedContribution {  });
+    }
+
+    /* Take the fund value from the latest rounds first, with the rewards bought by it */
+    let mut remaining = amount;
+    let mut reward_amount = Uint128::zero();
+    let mut private_value = Uint128::zero();
+    for round in state.rounds.iter_mut().rev() {
+        if remaining.is_zero() {
+            break;
+        }
+        let key = (round.id, sender.clone());
+        let round_fund = ROUND_FUNDS.may_load(deps.storage, key.clone())?.unwrap_or_default();
+        if round_fund.is_zero() {
+            continue;
+        }
+        let value = remaining.min(round_fund);
+        let round_rewards = ROUND_REWARDS.may_load(deps.storage, key.clone())?.unwrap_or_default();
+        let rewards = round_rewards.multiply_ratio(value, round_fund);
+        ROUND_FUNDS.save(deps.storage, key.clone(), &(round_fund - value))?;
+        ROUND_REWARDS.save(deps.storage, key, &(round_rewards - rewards))?;
+
+        round.raised -= value;
+        round.sold_amount -= rewards;
+        match round.kind {
+            RoundKind::Private => {
+                state.private_sold_amount -= rewards;
+                private_value += value;
+            },
+            RoundKind::Public => state.public_sold_amount -= rewards,
+        }
+        reward_amount += rewards;
+        remaining -= value;
+    }
+    if !private_value.is_zero() {
+        // withdrawn private purchases are reserved again
+        let private_sold_fund = PRIVATE_SOLD_FUNDS.may_load(deps.storage, sender.clone())?.unwrap_or_default();
+        let reservation = outstanding_reservation(deps.storage, &sender, private_sold_fund)?;
+        PRIVATE_SOLD_FUNDS.save(deps.storage, sender.clone(), &private_sold_fund.saturating_sub(private_value))?;
+        state.reserved_allocations += outstanding_reservation(deps.storage, &sender, private_sold_fund.saturating_sub(private_value))? - reservation;
+    }
+
+    /* Return the share of each deposited asset, less the penalty sent to the treasury */
+    let rate = withdrawal_penalty_rate(&state, &penalty, env.block.time.seconds());
+    let treasury = deps.api.addr_humanize(&penalty.treasury)?.to_string();
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for asset in state.fund_assets.iter_mut() {
+        let key = (sender.clone(), asset.info.to_string());
+        let deposited = PARTICIPANT_FUNDS.may_load(deps.storage, key.clone())?.unwrap_or_default();
+        let withdrawn = deposited.multiply_ratio(amount, recp_info.fund_balance);
+        if withdrawn.is_zero() {
+            continue;
+        }
+        PARTICIPANT_FUNDS.save(deps.storage, key, &(deposited - withdrawn))?;
+
+        let private_withdrawn = withdrawn.multiply_ratio(private_value, amount);
+        asset.private_raised = asset.private_raised.saturating_sub(private_withdrawn);
+        asset.public_raised = asset.public_raised.saturating_sub(withdrawn - private_withdrawn);
+
+        let penalty_amount = withdrawn * rate;
+        if withdrawn > penalty_amount {
+            messages.push(fund_transfer_msg(&asset.info, sender.clone(), withdrawn - penalty_amount)?);
+        }
+        if !penalty_amount.is_zero() {
+            messages.push(fund_transfer_msg(&asset.info, treasury.clone(), penalty_amount)?);
+        }
+    }
+
+    recp_info.fund_balance -= amount;
+    recp_info.reward_balance -= reward_amount;
+    state.total_raised -= amount;
+    store_state(deps.storage, &state)?;
+    PARTICIPANTS.save(deps.storage, sender.clone(), &recp_info)?;
+
+    /* Update vesting, fair launch rewards are registered on claim */
+    if state.sale_mode == SaleMode::FixedPrice {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
+            msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
+                recp: sender,
+                amount: recp_info.reward_balance.u128().try_into().unwrap(),
+            })?,
+            funds: vec![],
+        }));
+    }
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("method", "withdraw_contribution"),
+            attr("amount", amount.to_string()),
+            attr("reward_amount", reward_amount.to_string()),
+            attr("penalty_rate", rate.to_string()),
+        ]))
+}
+
+/// Fund asset amounts owed to the owner of a succeeded sale.
+fn sale_proceeds(deps: Deps, env: &Env, state: &State) -> Result<Vec<(AssetInfo, Uint128)>, ContractError> {
+    let mut proceeds = vec![];
+    if state.sale_mode != SaleMode::FixedPrice {
+        // escrowed funds stay in the contract until claimed, proceeds are the deposits left once refunds are paid
+        if (state.registered_participants as usize) < state.userlist.len() {
+            return Err(ContractError::AllocationsNotRegistered {  });
+        }
+        for asset in state.fund_assets.iter() {
+            let amount = ESCROW_PROCEEDS.may_load(deps.storage, asset.info.to_string())?.unwrap_or_default();
+            if !amount.is_zero() {
+                proceeds.push((asset.info.clone(), amount));
+            }
+        }
+        return Ok(proceeds);
+    }
+
+    for asset in state.fund_assets.iter() {
+        let fund_balance = match &asset.info {
+            AssetInfo::NativeToken { denom } => query_balance(deps, env.contract.address.clone(), denom.to_string())?,
+            AssetInfo::Token { contract_addr } => query_token_balance(deps, deps.api.addr_validate(contract_addr)?, env.contract.address.clone())?,
+        };
+        if !fund_balance.is_zero() {
+            proceeds.push((asset.info.clone(), fund_balance));
+        }
+    }
+    Ok(proceeds)
+}
+
+pub fn execute_withdraw_funds(deps: DepsMut, env: Env, info: MessageInfo, receiver: String) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let receiver_addr = deps.api.addr_validate(&receiver)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_not_paused(&state)?;
+
+    match sale_outcome(deps.as_ref(), &state, env.block.time.seconds())? {
+        SaleOutcome::Pending => return Err(ContractError::StillInProgress {  }),
+        SaleOutcome::Failed => return Err(ContractError::SaleFailed {  }),
+        SaleOutcome::Cancelled => return Err(ContractError::SaleCancelled {  }),
+        SaleOutcome::Succeeded => {}
+    }
+    if state.sale_mode != SaleMode::FixedPrice && state.proceeds_withdrawn {
+        return Err(ContractError::AlreadyWithdrawn {  });
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (asset_info, amount) in sale_proceeds(deps.as_ref(), &env, &state)? {
+        messages.push(fund_transfer_msg(&asset_info, receiver_addr.to_string(), amount)?);
+    }
+    state.proceeds_withdrawn = true;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "withdraw_funds"))
+}
+
+pub fn execute_withdraw_unsold_token(deps: DepsMut, env: Env, info: MessageInfo, receiver: String) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let receiver_addr = deps.api.addr_validate(&receiver)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_not_paused(&state)?;
+
+    match sale_outcome(deps.as_ref(), &state, env.block.time.seconds())? {
+        SaleOutcome::Pending => return Err(ContractError::StillInProgress {  }),
+        SaleOutcome::Failed => return Err(ContractError::SaleFailed {  }),
+        SaleOutcome::Cancelled => return Err(ContractError::SaleCancelled {  }),
+        SaleOutcome::Succeeded => {}
+    }
+
+    /* Sold rewards stay in escrow until sent to vesting on finalize */
+    let sold_amount = state.private_sold_amount + state.public_sold_amount;
+    let unsold_amount = state.escrowed_rewards.saturating_sub(sold_amount);
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !unsold_amount.is_zero() {
+        messages.push(reward_transfer_msg(&state, deps.api, receiver_addr.to_string(), unsold_amount)?);
+    }
+    state.escrowed_rewards -= unsold_amount;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "withdraw_unsold_token"))
+}
+
+fn validate_finalize(deps: Deps, finalize: FinalizeInfo) -> Result<FinalizeConfig, ContractError> {
+    let total_share = finalize.proceeds_receivers.iter()
+        .fold(Decimal::zero(), |total, receiver| total + receiver.share);
+    if total_share != Decimal::one() {
+        return Err(ContractError::InvalidInput {  });
+    }
+
+    let mut proceeds_receivers = vec![];
+    for receiver in finalize.proceeds_receivers {
+        proceeds_receivers.push(ProceedsReceiver {
+            address: deps.api.addr_canonicalize(&receiver.address)?,
+            share: receiver.share,
+        });
+    }
+    Ok(FinalizeConfig {
+        proceeds_receivers,
+        unsold_receiver: deps.api.addr_canonicalize(&finalize.unsold_receiver)?,
+        vesting_start_offset: finalize.vesting_start_offset,
+    })
+}
+
+pub fn execute_set_finalize_config(deps: DepsMut, info: MessageInfo, finalize: Option<FinalizeInfo>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.finalize = match finalize {
+        Some(finalize) => Some(validate_finalize(deps.as_ref(), finalize)?),
+        None => None,
+    };
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_finalize_config"),
+    ]))
+}
+
+pub fn execute_finalize(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    let config = state.finalize.clone()
+        .ok_or(ContractError::FinalizeNotConfigured {  })?;
+    assert_not_paused(&state)?;
+
+    /* Settle escrowed bids and contributions */
+    let escrowed = matches!(state.sale_mode, SaleMode::SealedBid { .. } | SaleMode::FairLaunch { .. });
+    if escrowed && !state.cancelled && state.fill_ratio.is_none() {
+        settle_sale(deps.as_ref(), &env, &mut state)?;
+    }
+
+    let outcome = sale_outcome(deps.as_ref(), &state, env.block.time.seconds())?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut proceeds: Vec<String> = vec![];
+    let mut vesting_start_time = None;
+    match outcome {
+        SaleOutcome::Pending => return Err(ContractError::StillInProgress {  }),
+        SaleOutcome::Succeeded => {
+            /* Split the proceeds, the last receiver takes the rounding remainder */
+            if !state.proceeds_withdrawn {
+                for (asset_info, amount) in sale_proceeds(deps.as_ref(), &env, &state)? {
+                    let mut remaining = amount;
+                    for (index, receiver) in config.proceeds_receivers.iter().enumerate() {
+                        let share = if index + 1 == config.proceeds_receivers.len() { remaining } else { amount * receiver.share };
+                        remaining -= share;
+                        if !share.is_zero() {
+                            messages.push(fund_transfer_msg(&asset_info, deps.api.addr_humanize(&receiver.address)?.to_string(), share)?);
+                        }
+                    }
+                    proceeds.push(format!("{}{}", amount, asset_info));
+                }
+                state.proceeds_withdrawn = true;
+            }
+
+            /* Escrowed allocations can't be registered once vesting started */
+            if state.sale_mode != SaleMode::FixedPrice && (state.registered_participants as usize) < state.userlist.len() {
+                return Err(ContractError::AllocationsNotRegistered {  });
+            }
+
+            /* Start vesting at the configured offset, not in the past */
+            let start_time = (sale_end_time(&state) + config.vesting_start_offset).max(env.block.time.seconds());
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
+                msg: to_binary(&vesting::msg::ExecuteMsg::SetStartTime { new_start_time: start_time })?,
+                funds: vec![],
+            }));
+            vesting_start_time = Some(start_time);
+        },
+        SaleOutcome::Failed | SaleOutcome::Cancelled => {},
+    }
+
+    /* Send the sold rewards to vesting and return the rest, all of it when the sale did not succeed */
+    let sold_amount = match outcome {
+        SaleOutcome::Succeeded => (state.private_sold_amount + state.public_sold_amount).min(state.escrowed_rewards),
+        _ => Uint128::zero(),
+    };
+    // rewards of participants blocked in vesting have no recipient there, they stay until recovered
+    let frozen_amount = state.frozen_rewards.min(sold_amount);
+    let unsold_amount = state.escrowed_rewards - sold_amount;
+    if sold_amount > frozen_amount {
+        messages.push(reward_transfer_msg(&state, deps.api, deps.api.addr_humanize(&state.vesting)?.to_string(), sold_amount - frozen_amount)?);
+    }
+    if !unsold_amount.is_zero() {
+        messages.push(reward_transfer_msg(&state, deps.api, deps.api.addr_humanize(&config.unsold_receiver)?.to_string(), unsold_amount)?);
+    }
+
+    state.escrowed_rewards = Uint128::zero();
+    state.frozen_rewards = frozen_amount;
+    state.finalized = true;
+    store_state(deps.storage, &state)?;
+
+    let event = Event::new("presale_finalized")
+        .add_attribute("outcome", format!("{:?}", outcome).to_lowercase())
+        .add_attribute("total_raised", state.total_raised.to_string())
+        .add_attribute("sold_amount", sold_amount.to_string())
+        .add_attribute("unsold_amount", unsold_amount.to_string())
+        .add_attribute("proceeds", proceeds.join(","))
+        .add_attribute("participants", state.userlist.len().to_string())
+        .add_attribute("clearing_price", state.clearing_price.unwrap_or_default().to_string())
+        .add_attribute("fill_ratio", state.fill_ratio.unwrap_or_default().to_string())
+        .add_attribute("vesting_start_time", vesting_start_time.map_or("".to_string(), |time| time.to_string()))
+        .add_attribute("frozen_amount", frozen_amount.to_string());
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(event)
+        .add_attribute("method", "finalize"))
+}
+
+pub fn execute_cancel_sale(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if state.cancelled {
+        return Err(ContractError::SaleCancelled {  });
+    }
+    if state.proceeds_withdrawn {
+        return Err(ContractError::AlreadyWithdrawn {  });
+    }
+
+    /* Return all escrowed reward tokens to the project */
+    let reward_balance = state.escrowed_rewards;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !reward_balance.is_zero() {
+        messages.push(reward_transfer_msg(&state, deps.api, info.sender.to_string(), reward_balance)?);
+    }
+
+    state.cancelled = true;
+    state.escrowed_rewards = Uint128::zero();
+    state.frozen_rewards = Uint128::zero();
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "cancel_sale"),
+            attr("returned_rewards", reward_balance.to_string()),
+        ]))
+}
+
+pub fn execute_claim_refund(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+    let sender = info.sender.to_string();
+
+    let outcome = sale_outcome(deps.as_ref(), &state, env.block.time.seconds())?;
+    if outcome != SaleOutcome::Failed && outcome != SaleOutcome::Cancelled {
+        return Err(ContractError::SaleNotFailed {  });
+    }
+    assert_not_paused(&state)?;
+    assert_not_blocked(deps.storage, &sender)?;
+
+    let (amount, messages) = refund_participant(deps, &state, &sender, &sender)?;
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("method", "claim_refund"),
+            attr("amount", amount.to_string()),
+        ]))
+}
+
+/// Returns all contributions of the participant to the receiver and removes it from vesting, with the refunded fund value.
+fn refund_participant(deps: DepsMut, state: &State, participant: &str, receiver: &str) -> Result<(Uint128, Vec<CosmosMsg>), ContractError> {
+    let recp_info = PARTICIPANTS.may_load(deps.storage, participant.to_string())?
+        .ok_or(ContractError::NothingToRefund {  })?;
+    if recp_info.fund_balance.is_zero() {
+        return Err(ContractError::NothingToRefund {  });
+    }
+
+    /* Reset participant, refunded users can't claim twice */
+    PARTICIPANTS.remove(deps.storage, participant.to_string());
+    PRIVATE_SOLD_FUNDS.remove(deps.storage, participant.to_string());
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for asset in state.fund_assets.iter() {
+        let key = (participant.to_string(), asset.info.to_string());
+        let amount = PARTICIPANT_FUNDS.may_load(deps.storage, key.clone())?.unwrap_or_default();
+        if !amount.is_zero() {
+            PARTICIPANT_FUNDS.remove(deps.storage, key);
+            messages.push(fund_transfer_msg(&asset.info, receiver.to_string(), amount)?);
+        }
+    }
+
+    /* Remove from vesting, resets are accepted also after the vesting start */
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::ResetRecipient { recp: participant.to_string() })?,
+        funds: vec![],
+    }));
+    Ok((recp_info.fund_balance, messages))
+}
+
+pub fn execute_recover_frozen(deps: DepsMut, env: Env, info: MessageInfo, address: String, recovery: String) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !BLOCKLIST.has(deps.storage, address.clone()) {
+        return Err(ContractError::InvalidInput {  });
+    }
+    let recovery = deps.api.addr_validate(&recovery)?;
+
+    /* Contributions are frozen once owed back, unspent escrow included, paid ones are sale proceeds */
+    let outcome = sale_outcome(deps.as_ref(), &state, env.block.time.seconds())?;
+    let (amount, reward_amount, messages) = match outcome {
+        SaleOutcome::Failed | SaleOutcome::Cancelled => {
+            let (amount, messages) = refund_participant(deps, &state, &address, recovery.as_str())?;
+            (amount, Uint128::zero(), messages)
+        },
+        SaleOutcome::Succeeded if state.sale_mode != SaleMode::FixedPrice => {
+            recover_escrowed(deps, &env, &mut state, &address, recovery.as_str())?
+        },
+        _ => return Err(ContractError::SaleNotFailed {  }),
+    };
+    let event = Event::new("recover_frozen")
+        .add_attribute("operator", info.sender.as_str())
+        .add_attribute("address", &address)
+        .add_attribute("recovery", recovery.as_str())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("reward_amount", reward_amount.to_string());
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(event)
+        .add_attributes(vec![
+            attr("action", "recover_frozen"),
+        ]))
+}
+
+/// Settles the escrow of the blocked participant to the recovery address, with the rewards kept from vesting once finalized.
+/// Returns the refunded fund value and the recovered reward amount.
+fn recover_escrowed(deps: DepsMut, env: &Env, state: &mut State, participant: &str, recovery: &str) -> Result<(Uint128, Uint128, Vec<CosmosMsg>), ContractError> {
+    let price = clearing_price(state, env.block.time.seconds())
+        .ok_or(ContractError::StillInProgress {  })?;
+    let mut recp_info = PARTICIPANTS.may_load(deps.storage, participant.to_string())?
+        .ok_or(ContractError::NothingToClaim {  })?;
+    let recover_rewards = recp_info.frozen && state.finalized;
+    if recp_info.claimed && !recover_rewards {
+        return Err(ContractError::NothingToClaim {  });
+    }
+
+    /* Refund the unspent escrow, the allocation is registered as for any participant */
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut refund_value = Uint128::zero();
+    if !recp_info.claimed {
+        let (reward_amount, paid_value) = escrowed_purchase(deps.as_ref(), state, participant, &recp_info, price)?;
+        refund_value = recp_info.fund_balance.saturating_sub(paid_value);
+        messages = refund_fund_value(deps.storage, state, participant, recovery, recp_info.fund_balance, refund_value)?;
+        recp_info.reward_balance = reward_amount;
+        recp_info.fund_balance -= refund_value;
+        recp_info.claimed = true;
+    }
+
+    /* Rewards kept from vesting are recovered once finalized */
+    let mut reward_amount = Uint128::zero();
+    if recover_rewards {
+        reward_amount = recp_info.reward_balance;
+        recp_info.frozen = false;
+        state.frozen_rewards -= reward_amount;
+        store_state(deps.storage, state)?;
+        messages.push(reward_transfer_msg(state, deps.api, recovery.to_string(), reward_amount)?);
+    }
+    PARTICIPANTS.save(deps.storage, participant.to_string(), &recp_info)?;
+    Ok((refund_value, reward_amount, messages))
+}
+
+pub fn execute_claim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let sender = info.sender.to_string();
+
+    if state.sale_mode == SaleMode::FixedPrice {
+        return Err(ContractError::NothingToClaim {  });
+    }
+    assert_not_paused(&state)?;
+    assert_not_blocked(deps.storage, &sender)?;
+    let price = clearing_price(&state, env.block.time.seconds())
+        .ok_or(ContractError::StillInProgress {  })?;
+    match sale_outcome(deps.as_ref(), &state, env.block.time.seconds())? {
+        SaleOutcome::Failed => return Err(ContractError::SaleFailed {  }),
+        SaleOutcome::Cancelled => return Err(ContractError::SaleCancelled {  }),
+        _ => {}
+    }
+
+    let mut recp_info = PARTICIPANTS.may_load(deps.storage, sender.clone())?
+        .ok_or(ContractError::NothingToClaim {  })?;
+    if recp_info.claimed {
+        return Err(ContractError::AlreadyClaimed {  });
+    }
+
+    /* Bids are settled at the clearing price, contributions pro rata, the difference is refunded */
+    let (reward_amount, paid_value) = escrowed_purchase(deps.as_ref(), &state, &sender, &recp_info, price)?;
+    let refund_value = recp_info.fund_balance.saturating_sub(paid_value);
+    let mut messages = refund_fund_value(deps.storage, &state, &sender, &sender, recp_info.fund_balance, refund_value)?;
+
+    /* Update vesting, unless registered by `RegisterAllocations`, vesting starts once all are registered */
+    if !recp_info.registered && !state.finalized && !reward_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&state.vesting)?.to_string(),
+            msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
+                recp: sender.clone(),
+                amount: reward_amount.u128().try_into().unwrap(),
+            })?,
+            funds: vec![],
+        }));
+        recp_info.registered = true;
+        if recp_info.frozen {
+            recp_info.frozen = false;
+            state.frozen_rewards -= reward_amount;
+            store_state(deps.storage, &state)?;
+        }
+    }
+    recp_info.reward_balance = reward_amount;
+    recp_info.fund_balance -= refund_value;
+    recp_info.claimed = true;
+    PARTICIPANTS.save(deps.storage, sender, &recp_info)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("method", "claim"),
+            attr("reward_amount", recp_info.reward_balance.to_string()),
+            attr("refund", refund_value.to_string()),
+        ]))
+}
+
+/// Reward amount bought by the escrowed participant and fund value paid for it at the clearing price.
+fn escrowed_purchase(deps: Deps, state: &State, participant: &str, recp_info: &Participant, price: Uint128) -> StdResult<(Uint128, Uint128)> {
+    // claimed participants keep the settled reward, their fund balance is the paid value
+    if recp_info.claimed {
+        return Ok((recp_info.reward_balance, recp_info.fund_balance));
+    }
+
+    match state.sale_mode {
+        SaleMode::FairLaunch { target_raise } => {
+            let paid_value = recp_info.fund_balance * state.fill_ratio.unwrap_or_default();
+            Ok((paid_value.multiply_ratio(state.total_rewards_amount, target_raise), paid_value))
+        },
+        _ => {
+            let reward_amount = match state.sale_mode {
+                SaleMode::SealedBid { .. } => sealed_bid_reward(deps.storage, state, participant, price)?,
+                _ => recp_info.reward_balance,
+            };
+            let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
+            Ok((reward_amount, calc_reward_value(state, price, reward_amount, reward_decimals)?))
+        },
+    }
+}
+
+pub fn execute_register_allocations(deps: DepsMut, env: Env, limit: Option<u32>) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    if state.sale_mode == SaleMode::FixedPrice {
+        return Err(ContractError::UnsupportedSaleMode {  });
+    }
+    assert_not_paused(&state)?;
+    let price = clearing_price(&state, env.block.time.seconds())
+        .ok_or(ContractError::StillInProgress {  })?;
+    match sale_outcome(deps.as_ref(), &state, env.block.time.seconds())? {
+        SaleOutcome::Pending => return Err(ContractError::StillInProgress {  }),
+        SaleOutcome::Failed => return Err(ContractError::SaleFailed {  }),
+        SaleOutcome::Cancelled => return Err(ContractError::SaleCancelled {  }),
+        SaleOutcome::Succeeded => {}
+    }
+
+    /* Register the next participants not registered on claim */
+    let vesting = deps.api.addr_humanize(&state.vesting)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = state.registered_participants as usize;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut skipped: Vec<String> = vec![];
+    for user in state.userlist.iter().skip(start).take(limit) {
+        let mut recp_info = match PARTICIPANTS.may_load(deps.storage, user.clone())? {
+            Some(recp_info) => recp_info,
+            None => continue,
+        };
+        let (reward_amount, paid_value) = escrowed_purchase(deps.as_ref(), &state, user, &recp_info, price)?;
+
+        // deposits left once the refund owed is paid are proceeds, in the participant's own assets
+        let refund_value = recp_info.fund_balance.saturating_sub(paid_value);
+        for (asset_info, deposited, refund) in asset_refunds(deps.storage, &state, user, recp_info.fund_balance, refund_value)? {
+            if deposited > refund {
+                ESCROW_PROCEEDS.update(deps.storage, asset_info.to_string(), |proceeds| -> StdResult<_> {
+                    Ok(proceeds.unwrap_or_default() + deposited - refund)
+                })?;
+            }
+        }
+
+        if recp_info.registered || reward_amount.is_zero() {
+            continue;
+        }
+        // vesting rejects allocations of blocked addresses, their rewards stay here
+        if query_vesting_blocked(deps.as_ref(), vesting.clone(), user.clone())? {
+            if !recp_info.frozen {
+                recp_info.frozen = true;
+                recp_info.reward_balance = reward_amount;
+                PARTICIPANTS.save(deps.storage, user.clone(), &recp_info)?;
+                state.frozen_rewards += reward_amount;
+            }
+            skipped.push(user.clone());
+            continue;
+        }
+        recp_info.registered = true;
+        PARTICIPANTS.save(deps.storage, user.clone(), &recp_info)?;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vesting.to_string(),
+            msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient {
+                recp: user.clone(),
+                amount: reward_amount.u128().try_into().unwrap(),
+            })?,
+            funds: vec![],
+        }));
+    }
+    let end = (start + limit).min(state.userlist.len());
+    state.registered_participants = end as u64;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("method", "register_allocations"),
+            attr("registered", (end - start).to_string()),
+            attr("remaining", (state.userlist.len() - end).to_string()),
+            attr("skipped", skipped.join(",")),
+        ]))
+}
+
+/// Reward amount won by the sealed bid, bids at the clearing price are partially filled.
+fn sealed_bid_reward(storage: &dyn Storage, state: &State, sender: &str, clearing_price: Uint128) -> StdResult<Uint128> {
+    let bid = match BIDS.may_load(storage, sender.to_string())? {
+        Some(bid) if bid.revealed && bid.price >= clearing_price => bid,
+        _ => return Ok(Uint128::zero()),
+    };
+    if bid.price > clearing_price {
+        return Ok(bid.amount);
+    }
+    Ok(bid.amount * state.fill_ratio.unwrap_or_default())
+}
+
+/// Bid commitment = hex(sha256(contract || bidder || price || amount || salt)), contract and bidder prefixed
+/// by their 4 byte big-endian length, numbers as 16 byte big-endian, salt as raw bytes.
+pub fn bid_commitment(contract: &str, bidder: &str, price: Uint128, amount: Uint128, salt: &str) -> String {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(length_prefixed(contract.as_bytes()));
+    hasher.update(length_prefixed(bidder.as_bytes()));
+    hasher.update(price.u128().to_be_bytes());
+    hasher.update(amount.u128().to_be_bytes());
+    hasher.update(salt.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+pub fn execute_commit_bid(deps: DepsMut, env: Env, sender: String, asset_info: AssetInfo, amount: Uint128, commitment: String) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+    let asset_index = fund_asset_index(&state, &asset_info)?;
+
+    if !matches!(state.sale_mode, SaleMode::SealedBid { .. }) {
+        return Err(ContractError::UnsupportedSaleMode {  });
+    }
+    if state.cancelled {
+        return Err(ContractError::SaleCancelled {  });
+    }
+    if state.escrowed_rewards < state.total_rewards_amount {
+        return Err(ContractError::RewardsNotFunded {  });
+    }
+
+    /* Bids are committed while a round is in progress */
+    if active_round_index(&state, env.block.time.seconds()).is_none() {
+        return Err(ContractError::RoundNotInProgress {  });
+    }
+    if BIDS.has(deps.storage, sender.clone()) {
+        return Err(ContractError::AlreadyCommitted {  });
+    }
+    assert_not_paused(&state)?;
+    assert_not_blocked(deps.storage, &sender)?;
+    assert_attested(deps.as_ref(), &state, &sender)?;
+    let mut commitment_buf = [0u8; 32];
+    hex::decode_to_slice(&commitment, &mut commitment_buf)?;
+
+    /* Escrow funds */
+    let oracle_price = query_oracle_price(deps.as_ref(), &env, &state, &state.fund_assets[asset_index])?;
+    let value = calc_fund_value(&state, &state.fund_assets[asset_index], amount, oracle_price)?;
+    let recp_info = Participant {
+        fund_balance: value,
+        reward_balance: Uint128::zero(),
+        claimed: false,
+        registered: false,
+        frozen: false,
+    };
+    state.userlist.push(sender.clone());
+    state.total_raised += value;
+    state.fund_assets[asset_index].public_raised += amount;
+
+    store_state(deps.storage, &state)?;
+    PARTICIPANTS.save(deps.storage, sender.clone(), &recp_info)?;
+    PARTICIPANT_FUNDS.save(deps.storage, (sender.clone(), asset_info.to_string()), &amount)?;
+    BIDS.save(deps.storage, sender, &Bid {
+        commitment: hex::encode(commitment_buf),
+        price: Uint128::zero(),
+        amount: Uint128::zero(),
+        revealed: false,
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("method", "commit_bid"),
+        attr("amount", amount.to_string()),
+        attr("value", value.to_string()),
+    ]))
+}
+
+pub fn execute_reveal_bid(deps: DepsMut, env: Env, info: MessageInfo, price: Uint128, amount: Uint128, salt: String) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+    let sender = info.sender.to_string();
+
+    let (reserve_price, reveal_period) = match state.sale_mode {
+        SaleMode::SealedBid { reserve_price, reveal_period } => (reserve_price, reveal_period),
+        _ => return Err(ContractError::UnsupportedSaleMode {  }),
+    };
+
+    /* Check if reveal period in progress */
+    let end_time = sale_end_time(&state);
+    if env.block.time.seconds() < end_time || env.block.time.seconds() >= end_time + reveal_period {
+        return Err(ContractError::RevealNotInProgress {  });
+    }
+
+    let mut bid = BIDS.may_load(deps.storage, sender.clone())?
+        .ok_or(ContractError::InvalidCommitment {  })?;
+    if bid.revealed || bid_commitment(env.contract.address.as_str(), &sender, price, amount, &salt) != bid.commitment {
+        return Err(ContractError::InvalidCommitment {  });
+    }
+    if price < reserve_price || amount.is_zero() {
+        return Err(ContractError::InvalidInput {  });
+    }
+
+    /* Bid must be covered by the escrow */
+    let recp_info = PARTICIPANTS.load(deps.storage, sender.clone())?;
+    let reward_decimals = query_decimals(deps.as_ref(), deps.api.addr_humanize(&state.reward_token)?.to_string())?;
+    if calc_reward_value(&state, price, amount, reward_decimals)? > recp_info.fund_balance {
+        return Err(ContractError::BidExceedsEscrow {  });
+    }
+
+    bid.price = price;
+    bid.amount = amount;
+    bid.revealed = true;
+    BIDS.save(deps.storage, sender, &bid)?;
+    BID_DEMAND.update(deps.storage, price.u128(), |demand| -> StdResult<_> {
+        Ok(demand.unwrap_or_default() + amount)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("method", "reveal_bid"),
+        attr("price", price.to_string()),
+        attr("amount", amount.to_string()),
+    ]))
+}
+
+pub fn execute_settle(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let mut state: State = read_state(deps.storage)?;
+
+    settle_sale(deps.as_ref(), &env, &mut state)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("method", "settle"),
+        attr("clearing_price", state.clearing_price.unwrap_or_default().to_string()),
+        attr("fill_ratio", state.fill_ratio.unwrap_or_default().to_string()),
+        attr("sold_amount", state.public_sold_amount.to_string()),
+    ]))
+}
+
+/// Settles sealed bids after the reveal period, or fair launch contributions after the sale.
+fn settle_sale(deps: Deps, env: &Env, state: &mut State) -> Result<(), ContractError> {
+    match state.sale_mode {
+        SaleMode::SealedBid { reserve_price, reveal_period } => {
+            if env.block.time.seconds() < sale_end_time(state) + reveal_period {
+                return Err(ContractError::StillInProgress {  });
+            }
+            if state.fill_ratio.is_some() {
+                return Err(ContractError::AlreadySettled {  });
+            }
+            settle_sealed_bids(deps.storage, state, reserve_price)?;
+        },
+        SaleMode::FairLaunch { target_raise } => {
+            if env.block.time.seconds() < sale_end_time(state) {
+                return Err(ContractError::StillInProgress {  });
+            }
+            if state.fill_ratio.is_some() {
+                return Err(ContractError::AlreadySettled {  });
+            }
+            settle_fair_launch(deps, state, target_raise)?;
+        },
+        _ => return Err(ContractError::UnsupportedSaleMode {  }),
+    }
+    Ok(())
+}
+
+/// Fills revealed bids from the highest price down until the supply is exhausted.
+fn settle_sealed_bids(storage: &dyn Storage, state: &mut State, reserve_price: Uint128) -> StdResult<()> {
+    let mut remaining = state.total_rewards_amount;
+    let mut clearing_price = reserve_price;
+    let mut fill_ratio = Decimal::one();
+    for item in BID_DEMAND.range(storage, None, None, Order::Descending) {
+        let (price, demand) = item?;
+        clearing_price = Uint128::from(price);
+        if demand >= remaining {
+            fill_ratio = Decimal::from_ratio(remaining, demand);
+            remaining = Uint128::zero();
+            break;
+        }
+        remaining -= demand;
+    }
+
+    state.public_sold_amount = state.total_rewards_amount - remaining;
+    state.clearing_price = Some(clearing_price);
+    state.fill_ratio = Some(fill_ratio);
+    Ok(())
+}
+
+/// Oversubscribed contributions are filled pro rata up to the target raise.
+fn settle_fair_launch(deps: Deps, state: &mut State, target_raise: Uint128) -> StdResult<()> {
+    let reward_decimals = query_decimals(deps, deps.api.addr_humanize(&state.reward_token)?.to_string())?;
+    let fill_ratio = if state.total_raised > target_raise {
+        Decimal::from_ratio(target_raise, state.total_raised)
+    } else {
+        Decimal::one()
+    };
+
+    state.public_sold_amount = state.total_raised.min(target_raise)
+        .multiply_ratio(state.total_rewards_amount, target_raise);
+    // price implied by selling the supply for the target raise
+    state.clearing_price = Some(target_raise.multiply_ratio(
+        Uint128::from(ACCURACY).checked_mul(Uint128::from(10u128).wrapping_pow(reward_decimals))?,
+        state.total_rewards_amount.checked_mul(Uint128::from(10u128).wrapping_pow(value_decimals(state)))?,
+    ));
+    state.fill_ratio = Some(fill_ratio);
+    Ok(())
+}
+
+/// Refunds `refund_value` out of participant's `fund_balance` to the receiver, split over the deposited assets pro rata.
+fn refund_fund_value(storage: &mut dyn Storage, state: &State, participant: &str, receiver: &str, fund_balance: Uint128, refund_value: Uint128) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (asset_info, deposited, amount) in asset_refunds(storage, state, participant, fund_balance, refund_value)? {
+        if !amount.is_zero() {
+            PARTICIPANT_FUNDS.save(storage, (participant.to_string(), asset_info.to_string()), &(deposited - amount))?;
+            messages.push(fund_transfer_msg(&asset_info, receiver.to_string(), amount)?);
+        }
+    }
+    Ok(messages)
+}
+
+/// Deposited amount and refund of each fund asset, refunds follow the participant's own asset mix.
+fn asset_refunds(storage: &dyn Storage, state: &State, participant: &str, fund_balance: Uint128, refund_value: Uint128) -> StdResult<Vec<(AssetInfo, Uint128, Uint128)>> {
+    let mut refunds = vec![];
+    for asset in state.fund_assets.iter() {
+        let deposited = PARTICIPANT_FUNDS.may_load(storage, (participant.to_string(), asset.info.to_string()))?.unwrap_or_default();
+        let amount = if refund_value.is_zero() { Uint128::zero() } else { deposited.multiply_ratio(refund_value, fund_balance) };
+        refunds.push((asset.info.clone(), deposited, amount));
+    }
+    Ok(refunds)
+}
+
+/************************************ Query *************************************/
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::ParticipantsCount {} => to_binary(&query_count(deps)?),
+        QueryMsg::GetParticipants { page, limit } => to_binary(&query_participants(deps, page, limit)?),
+        QueryMsg::GetParticipant { user } => to_binary(&query_participant(deps, user)?),
+        QueryMsg::GetBid { user } => to_binary(&query_bid(deps, user)?),
+        QueryMsg::GetSaleStatus { } => to_binary( &query_sale_status(deps, env)? ),
+        QueryMsg::PresaleInfo { } => to_binary( &query_presale_info(deps)? ),
+        QueryMsg::CurrentRound { } => to_binary( &query_current_round(deps, env)? ),
+        QueryMsg::UpcomingRounds { } => to_binary( &query_upcoming_rounds(deps, env)? ),
+        QueryMsg::Whitelist { start_after, limit } => to_binary(&query_whitelist(deps, start_after, limit)?),
+        QueryMsg::StakingTier { user } => to_binary(&query_staking_tier(deps, user)?),
+        QueryMsg::LockAllocation { user } => to_binary(&query_lock_allocation(deps, env, user)?),
+        QueryMsg::LotteryEntry { user } => to_binary(&query_lottery_entry(deps, user)?),
+        QueryMsg::TokenAllocation { user } => to_binary(&query_token_allocation(deps, user)?),
+        QueryMsg::Blocked { address } => to_binary(&BlockedResponse { block: BLOCKLIST.may_load(deps.storage, address)? }),
+        QueryMsg::UsedToken { token_id } => to_binary(&UsedTokenResponse { claimed_by: USED_TOKENS.may_load(deps.storage, token_id)? }),
+        QueryMsg::SimulateDeposit { asset, amount } => to_binary(
+            &query_simulate_deposit(deps, env, asset, amount).map_err(|e| StdError::generic_err(e.to_string()))?
+        ),
+    }
+}
+
+fn query_count(deps: Deps) -> StdResult<ParticipantsCountResponse> {
+    let state: State = read_state(deps.storage)?;
+    Ok(ParticipantsCountResponse { count: state.userlist.len() as u64 })
 }
 
 fn query_participants(deps: Deps, page: u64, limit: u64) -> StdResult<GetParticipantsResponse> {
@@ -419,25 +2586,211 @@ fn query_participants(deps: Deps, page: u64, limit: u64) -> StdResult<GetPartici
 }
 
 fn query_participant(deps: Deps, user: String) -> StdResult<GetParticipantResponse> {
-    let data = PARTICIPANTS.load(deps.storage, user).unwrap_or(Participant { fund_balance: Uint128::zero(), reward_balance: Uint128::zero() });
+    let data = PARTICIPANTS.load(deps.storage, user).unwrap_or(Participant { fund_balance: Uint128::zero(), reward_balance: Uint128::zero(), claimed: false, registered: false, frozen: false });
     Ok(GetParticipantResponse { data })
 }
 
-fn query_sale_status(deps: Deps) -> StdResult<GetSaleStatusResponse> {
+fn query_bid(deps: Deps, user: String) -> StdResult<GetBidResponse> {
+    Ok(GetBidResponse { bid: BIDS.may_load(deps.storage, user)? })
+}
+
+fn query_sale_status(deps: Deps, env: Env) -> StdResult<GetSaleStatusResponse> {
     let state: State = read_state(deps.storage)?;
-    Ok(GetSaleStatusResponse { private_sold_amount: state.private_sold_amount, public_sold_amount: state.public_sold_amount })
+    Ok(GetSaleStatusResponse {
+        private_sold_amount: state.private_sold_amount,
+        public_sold_amount: state.public_sold_amount,
+        total_raised: state.total_raised,
+        escrowed_rewards: state.escrowed_rewards,
+        outcome: sale_outcome(deps, &state, env.block.time.seconds())?,
+        clearing_price: clearing_price(&state, env.block.time.seconds()),
+        fill_ratio: state.fill_ratio,
+        fund_assets: state.fund_assets,
+        finalized: state.finalized,
+        registered_participants: state.registered_participants,
+        frozen_rewards: state.frozen_rewards,
+    })
 }
 
 fn query_presale_info(deps: Deps) -> StdResult<PresaleInfoResponse> {
     let state: State = read_state(deps.storage)?;
     Ok(PresaleInfoResponse {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        fund_assets: state.fund_assets,
+        legacy_whitelist_until: state.legacy_whitelist_until,
+        public_whitelist: state.public_whitelist,
+        whitelist_mode: state.whitelist_mode,
+        whitelist_signer: state.whitelist_signer,
+        sale_mode: state.sale_mode,
         accuracy: Uint128::from(ACCURACY),
-        exchange_rate: state.exchange_rate,
-        presale_period: state.presale_period,
-        public_start_time: state.public_start_time,
-        private_start_time: state.private_start_time,
-        total_rewards_amount: state.total_rewards_amount
+        rounds: state.rounds,
+        total_rewards_amount: state.total_rewards_amount,
+        soft_cap: state.soft_cap,
+        oracle: match state.oracle {
+            Some(oracle) => Some(OracleInfo {
+                contract: deps.api.addr_humanize(&oracle.contract)?.to_string(),
+                token_price: oracle.token_price,
+                max_price_age: oracle.max_price_age,
+            }),
+            None => None,
+        },
+        staking: match state.staking {
+            Some(staking) => Some(StakingInfo {
+                contract: deps.api.addr_humanize(&staking.contract)?.to_string(),
+                snapshot_time: staking.snapshot_time,
+                base_allocation: staking.base_allocation,
+                tiers: staking.tiers,
+            }),
+            None => None,
+        },
+        locking: match state.locking {
+            Some(locking) => Some(LockingInfo {
+                contract: deps.api.addr_humanize(&locking.contract)?.to_string(),
+                allocation_rate: locking.allocation_rate,
+                min_lock_age: locking.min_lock_age,
+                max_boost: locking.max_boost,
+                boost_period: locking.boost_period,
+            }),
+            None => None,
+        },
+        lottery: state.lottery,
+        nft_gate: match state.nft_gate {
+            Some(nft_gate) => Some(NftGateInfo {
+                collection: deps.api.addr_humanize(&nft_gate.collection)?.to_string(),
+                allocation_per_token: nft_gate.allocation_per_token,
+            }),
+            None => None,
+        },
+        finalize: match state.finalize {
+            Some(finalize) => Some(FinalizeInfo {
+                proceeds_receivers: finalize.proceeds_receivers.iter().map(|receiver| Ok(ProceedsReceiverInfo {
+                    address: deps.api.addr_humanize(&receiver.address)?.to_string(),
+                    share: receiver.share,
+                })).collect::<StdResult<Vec<_>>>()?,
+                unsold_receiver: deps.api.addr_humanize(&finalize.unsold_receiver)?.to_string(),
+                vesting_start_offset: finalize.vesting_start_offset,
+            }),
+            None => None,
+        },
+        withdrawal_penalty: match state.withdrawal_penalty {
+            Some(penalty) => Some(WithdrawalPenaltyInfo {
+                treasury: deps.api.addr_humanize(&penalty.treasury)?.to_string(),
+                start_rate: penalty.start_rate,
+                end_rate: penalty.end_rate,
+            }),
+            None => None,
+        },
+        compliance: match state.compliance {
+            Some(compliance) => Some(deps.api.addr_humanize(&compliance)?.to_string()),
+            None => None,
+        },
+        guardian: match state.guardian {
+            Some(guardian) => Some(deps.api.addr_humanize(&guardian)?.to_string()),
+            None => None,
+        },
+        paused: state.paused_at.is_some(),
+        paused_at: state.paused_at,
+        extend_on_pause: state.extend_on_pause,
+        attestation: match state.attestation {
+            Some(gate) => Some(AttestationInfo {
+                registry: deps.api.addr_humanize(&gate.registry)?.to_string(),
+                blocked_jurisdictions: gate.blocked_jurisdictions,
+            }),
+            None => None,
+        },
+    })
+}
+
+fn query_current_round(deps: Deps, env: Env) -> StdResult<RoundResponse> {
+    let state: State = read_state(deps.storage)?;
+    let round = active_round_index(&state, env.block.time.seconds())
+        .map(|index| state.rounds[index].clone());
+    Ok(RoundResponse { round })
+}
+
+fn query_upcoming_rounds(deps: Deps, env: Env) -> StdResult<RoundsResponse> {
+    let state: State = read_state(deps.storage)?;
+    let rounds = state.rounds.into_iter()
+        .filter(|round| round.start_time > env.block.time.seconds())
+        .collect();
+    Ok(RoundsResponse { rounds })
+}
+
+fn query_whitelist(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<WhitelistResponse> {
+    let entries = read_whitelist(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(address, allo_info)| {
+            let fund_balance = PARTICIPANTS.may_load(deps.storage, address.clone())?.map(|p| p.fund_balance).unwrap_or_default();
+            let private_sold_fund = PRIVATE_SOLD_FUNDS.may_load(deps.storage, address.clone())?.unwrap_or_default();
+            Ok(WhitelistEntryResponse {
+                private_remaining: allo_info.private_allocation.saturating_sub(private_sold_fund),
+                public_remaining: (allo_info.public_allocation + private_sold_fund).saturating_sub(fund_balance),
+                address,
+                allo_info,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(WhitelistResponse { entries })
+}
+
+fn query_staking_tier(deps: Deps, user: String) -> StdResult<StakingTierResponse> {
+    let state: State = read_state(deps.storage)?;
+    let snapshot_bond = STAKE_SNAPSHOTS.may_load(deps.storage, user.clone())?.unwrap_or_default();
+    let (bond_amount, tier, allocation) = match &state.staking {
+        Some(staking) => {
+            let (bond_amount, tier) = staking_tier(deps, staking, &user)?;
+            (bond_amount, tier, tier_allocation(staking, tier))
+        },
+        None => (Uint128::zero(), None, Uint128::zero()),
+    };
+
+    Ok(StakingTierResponse {
+        snapshot_bond,
+        bond_amount,
+        tier: tier.map(|index| index as u64),
+        allocation,
+    })
+}
+
+fn query_lock_allocation(deps: Deps, env: Env, user: String) -> StdResult<LockAllocationResponse> {
+    let state: State = read_state(deps.storage)?;
+    let locking = state.locking.ok_or_else(|| StdError::generic_err("Locking not configured"))?;
+    let (locked_amount, lock_age, allocation) = lock_allocation(deps, &locking, &user, env.block.time.seconds())?;
+
+    Ok(LockAllocationResponse {
+        locked_amount,
+        lock_age,
+        eligible: allocation.is_some(),
+        allocation: allocation.unwrap_or_default(),
     })
 }
 
+fn query_lottery_entry(deps: Deps, user: String) -> StdResult<LotteryEntryResponse> {
+    Ok(LotteryEntryResponse {
+        tickets: LOTTERY_TICKETS.may_load(deps.storage, user.clone())?.unwrap_or_default(),
+        winner: LOTTERY_WINNERS.has(deps.storage, user),
+    })
+}
+
+fn query_token_allocation(deps: Deps, user: String) -> StdResult<TokenAllocationResponse> {
+    let allocation = TOKEN_ALLOCATIONS.may_load(deps.storage, user.clone())?.unwrap_or_default();
+    let fund_balance = PARTICIPANTS.may_load(deps.storage, user)?.map(|p| p.fund_balance).unwrap_or_default();
+    Ok(TokenAllocationResponse { allocation, remaining: allocation.saturating_sub(fund_balance) })
+}
+
+fn query_simulate_deposit(deps: Deps, env: Env, asset_info: AssetInfo, amount: Uint128) -> Result<SimulateDepositResponse, ContractError> {
+    let state: State = read_state(deps.storage)?;
+    let round = active_round_index(&state, env.block.time.seconds())
+        .map(|index| deposit_round(&state, index, env.block.time.seconds()))
+        .ok_or(ContractError::RoundNotInProgress {  })?;
+
+    let asset = &state.fund_assets[fund_asset_index(&state, &asset_info)?];
+    let oracle_price = query_oracle_price(deps, &env, &state, asset)?;
+    let (accepted_amount, _, reward_amount, refund_amount) = fill_deposit(deps, &state, &round, asset, amount, None, active_reservations(&state, env.block.time.seconds()), oracle_price)?;
+
+    Ok(SimulateDepositResponse {
+        round_id: round.id,
+        reward_amount,
+        accepted_amount,
+        refund_amount,
+    })
+}
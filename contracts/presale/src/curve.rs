@@ -0,0 +1,81 @@
+use std::convert::TryFrom;
+
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint128, Uint256, Uint512};
+
+use crate::state::{PricingCurve, ACCURACY};
+
+pub struct CurveParams<'a> {
+    pub curve: &'a PricingCurve,
+    // Price of the first reward token = fund value / reward * ACCURACY.
+    pub base_price: Uint128,
+    pub reward_decimals: u32,
+    pub value_decimals: u32,
+}
+
+/// Fund value paid for the first `sold` reward tokens of the curve.
+/// Costs are cumulative, so a deposit pays the difference between two positions on the curve.
+fn cumulative_cost(params: &CurveParams, sold: Uint128) -> StdResult<Uint256> {
+    let value_unit = Uint256::from(10u128).checked_pow(params.value_decimals)?;
+    let reward_unit = Uint256::from(10u128).checked_pow(params.reward_decimals)?;
+    let base_price = Uint256::from(params.base_price);
+    let sold = Uint256::from(sold);
+
+    match params.curve {
+        PricingCurve::Linear { slope } => {
+            // price(x) = base_price + slope * x / reward_unit
+            // cost = (2 * reward_unit * base_price * sold + slope * sold^2) * value_unit / (2 * ACCURACY * reward_unit^2)
+            let numerator = Uint512::from(reward_unit.checked_mul(base_price)?.checked_mul(Uint256::from(2u128))?)
+                .checked_mul(Uint512::from(sold))?
+                .checked_add(Uint512::from(sold).checked_mul(Uint512::from(sold))?.checked_mul(Uint512::from(*slope))?)?
+                .checked_mul(Uint512::from(value_unit))?;
+            let denominator = Uint512::from(reward_unit)
+                .checked_mul(Uint512::from(reward_unit))?
+                .checked_mul(Uint512::from(2u128 * ACCURACY))?;
+            Ok(Uint256::try_from(numerator.checked_div(denominator)?)?)
+        },
+        PricingCurve::Exponential { growth, step } => {
+            // price is constant within a step and grows by `growth` after each step:
+            // amount = step * (growth^k - 1) / (growth - 1) + growth^k * (sold - k * step)
+            let step = Uint256::from(*step);
+            let growth = Decimal256::from(*growth);
+            let steps = u32::try_from(Uint128::try_from(sold.checked_div(step)?)?.u128())
+                .map_err(|_| StdError::generic_err("Curve position out of range"))?;
+            let multiplier = growth.checked_pow(steps)?;
+
+            let full_steps = (multiplier - Decimal256::one())
+                .checked_div(growth - Decimal256::one())
+                .map_err(|e| StdError::generic_err(e.to_string()))?
+                .checked_mul(Decimal256::from_ratio(step, 1u128))?;
+            let partial_step = multiplier.checked_mul(Decimal256::from_ratio(sold.checked_rem(step)?, 1u128))?;
+            let amount = full_steps.checked_add(partial_step)?;
+
+            Ok(amount.atomics().multiply_ratio(
+                base_price.checked_mul(value_unit)?,
+                Decimal256::one().atomics().checked_mul(Uint256::from(ACCURACY))?.checked_mul(reward_unit)?,
+            ))
+        },
+    }
+}
+
+/// Fund value of `amount` reward tokens bought after `sold` tokens.
+pub fn curve_cost(params: &CurveParams, sold: Uint128, amount: Uint128) -> StdResult<Uint128> {
+    let cost = cumulative_cost(params, sold.checked_add(amount)?)?
+        .checked_sub(cumulative_cost(params, sold)?)?;
+    Ok(Uint128::try_from(cost)?)
+}
+
+/// Largest reward amount, at most `max_amount`, that `value` buys after `sold` tokens.
+pub fn curve_reward_amount(params: &CurveParams, sold: Uint128, value: Uint128, max_amount: Uint128) -> StdResult<Uint128> {
+    let budget = cumulative_cost(params, sold)?.checked_add(Uint256::from(value))?;
+
+    let (mut low, mut high) = (Uint128::zero(), max_amount);
+    while low < high {
+        let mid = high - (high - low) / Uint128::from(2u128);
+        if cumulative_cost(params, sold.checked_add(mid)?)? <= budget {
+            low = mid;
+        } else {
+            high = mid - Uint128::from(1u128);
+        }
+    }
+    Ok(low)
+}
@@ -0,0 +1,79 @@
+use std::convert::TryInto;
+
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{pairing, G1Affine, G2Affine, G2Projective};
+use cosmwasm_std::Uint128;
+use sha2_v9::{Digest, Sha256};
+
+// Domain separation tag of the chained drand scheme, public key on G1 and signatures on G2.
+const DOMAIN: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+pub fn parse_public_key(public_key: &[u8]) -> Option<G1Affine> {
+    let bytes: [u8; 48] = public_key.try_into().ok()?;
+    Option::from(G1Affine::from_compressed(&bytes))
+}
+
+/// Message signed by the drand group for a round = sha256(previous signature || round).
+fn round_message(round: u64, previous_signature: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(previous_signature);
+    hasher.update(round.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Verifies the BLS signature of a chained drand beacon against the group public key.
+pub fn verify_beacon(public_key: &[u8], round: u64, previous_signature: &[u8], signature: &[u8]) -> bool {
+    let public_key = match parse_public_key(public_key) {
+        Some(public_key) => public_key,
+        None => return false,
+    };
+    let signature: [u8; 96] = match signature.try_into() {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let signature: G2Affine = match Option::from(G2Affine::from_compressed(&signature)) {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let message = round_message(round, previous_signature);
+    let hashed: G2Affine = <G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(&message, DOMAIN).into();
+    pairing(&public_key, &hashed) == pairing(&G1Affine::generator(), &signature)
+}
+
+/// Randomness of a beacon = sha256(signature).
+pub fn beacon_randomness(signature: &[u8]) -> Vec<u8> {
+    Sha256::digest(signature).to_vec()
+}
+
+/// Ticket drawn by the n-th draw from the beacon randomness, below the ticket count.
+pub fn draw_ticket(randomness: &[u8], draw: u64, tickets: Uint128) -> Uint128 {
+    let mut hasher = Sha256::new();
+    hasher.update(randomness);
+    hasher.update(draw.to_be_bytes());
+    let hash = hasher.finalize();
+    Uint128::from(u128::from_be_bytes(hash[..16].try_into().unwrap())) % tickets
+}
+
+/// Draws winners weighted by tickets without replacement, entrants are (address, tickets) in a fixed order.
+pub fn draw_winners(randomness: &[u8], mut entrants: Vec<(String, Uint128)>, winners: u64) -> Vec<String> {
+    let mut remaining: Uint128 = entrants.iter().map(|(_, tickets)| *tickets).sum();
+    let mut drawn = vec![];
+    for draw in 0..winners {
+        if entrants.is_empty() {
+            break;
+        }
+
+        let mut ticket = draw_ticket(randomness, draw, remaining);
+        let mut index = 0;
+        while ticket >= entrants[index].1 {
+            ticket -= entrants[index].1;
+            index += 1;
+        }
+
+        let (winner, tickets) = entrants.remove(index);
+        remaining -= tickets;
+        drawn.push(winner);
+    }
+    drawn
+}
@@ -31,6 +31,87 @@ pub enum ContractError {
     #[error("Exceed Allocation")]
     ExceedAllocation {},
 
+    #[error("Rewards Not Funded")]
+    RewardsNotFunded {},
+
+    #[error("Hard Cap Reached")]
+    HardCapReached {},
+
+    #[error("Asset Cap Reached")]
+    AssetCapReached {},
+
+    #[error("Round Not In Progress")]
+    RoundNotInProgress {},
+
+    #[error("Round Cap Reached")]
+    RoundCapReached {},
+
+    #[error("Invalid Oracle Price")]
+    InvalidPrice {},
+
+    #[error("Oracle Price Too Old")]
+    StalePrice {},
+
+    #[error("Snapshot Time Passed")]
+    SnapshotPassed {},
+
+    #[error("Supply Reserved For Stakers")]
+    SupplyReserved {},
+
+    #[error("Registration Closed")]
+    RegistrationClosed {},
+
+    #[error("Already Registered")]
+    AlreadyRegistered {},
+
+    #[error("No Tickets")]
+    NoTickets {},
+
+    #[error("Lottery Full")]
+    LotteryFull {},
+
+    #[error("Invalid Beacon")]
+    InvalidBeacon {},
+
+    #[error("Lottery Already Drawn")]
+    AlreadyDrawn {},
+
+    #[error("Lottery Not Drawn")]
+    LotteryNotDrawn {},
+
+    #[error("Not A Lottery Winner")]
+    NotWinner {},
+
+    #[error("Tickets Not Backed")]
+    TicketsNotBacked {},
+
+    #[error("No Token Allocation")]
+    NoTokenAllocation {},
+
+    #[error("Not Token Owner")]
+    NotTokenOwner {},
+
+    #[error("Token Already Used")]
+    TokenUsed {},
+
+    #[error("Paused")]
+    Paused {},
+
+    #[error("Address Blocked")]
+    Blocked {},
+
+    #[error("Attestation Required")]
+    NotAttested {},
+
+    #[error("Jurisdiction Blocked")]
+    BlockedJurisdiction {},
+
+    #[error("Permit Expired")]
+    PermitExpired {},
+
+    #[error("Permit Already Used")]
+    PermitUsed {},
+
     #[error("Wrong length")]
     WrongLength {},
 
@@ -38,5 +119,62 @@ pub enum ContractError {
     VerificationFailed {},
 
     #[error("Funds not paid")]
-    Funds {}    
+    Funds {},
+
+    #[error("Sale Failed")]
+    SaleFailed {},
+
+    #[error("Sale Cancelled")]
+    SaleCancelled {},
+
+    #[error("Sale Ended")]
+    SaleEnded {},
+
+    #[error("Withdrawal Disabled")]
+    WithdrawalDisabled {},
+
+    #[error("Exceed Contribution")]
+    ExceedContribution {},
+
+    #[error("Sale Not Failed")]
+    SaleNotFailed {},
+
+    #[error("Nothing To Refund")]
+    NothingToRefund {},
+
+    #[error("Nothing To Claim")]
+    NothingToClaim {},
+
+    #[error("Already Claimed")]
+    AlreadyClaimed {},
+
+    #[error("Already Withdrawn")]
+    AlreadyWithdrawn {},
+
+    #[error("Finalize Not Configured")]
+    FinalizeNotConfigured {},
+
+    #[error("Presale Finalized")]
+    Finalized {},
+
+    #[error("Unsupported Sale Mode")]
+    UnsupportedSaleMode {},
+
+    #[error("Already Committed")]
+    AlreadyCommitted {},
+
+    #[error("Invalid Commitment")]
+    InvalidCommitment {},
+
+    #[error("Reveal Not In Progress")]
+    RevealNotInProgress {},
+
+    #[error("Bid Exceeds Escrow")]
+    BidExceedsEscrow {},
+
+    #[error("Already Settled")]
+    AlreadySettled {},
+
+    #[error("Allocations Not Registered")]
+    AllocationsNotRegistered {},
 }
@@ -1,4 +1,6 @@
 pub mod contract;
+pub mod curve;
+pub mod drand;
 pub mod msg;
 pub mod state;
 pub mod error;
@@ -6,4 +8,6 @@ pub mod querier;
 pub mod types;
 
 #[cfg(test)]
-pub mod testing;
\ No newline at end of file
+mod mock_querier;
+#[cfg(test)]
+pub mod testing;
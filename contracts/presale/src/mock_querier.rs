@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Decimal, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use attestation::msg::{AttestationResponse, QueryMsg as AttestationQueryMsg};
+use attestation::state::Attestation;
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw721::{Cw721QueryMsg, OwnerOfResponse, TokensResponse};
+use locking::msg::{LockInfoResponse, QueryMsg as LockingQueryMsg};
+use locking::state::LockInfo;
+use staking::msg::{QueryMsg as StakingQueryMsg, StakerInfoResponse};
+use vesting::msg::{BlockedResponse as VestingBlockedResponse, QueryMsg as VestingQueryMsg};
+use vesting::state::BlockInfo as VestingBlockInfo;
+
+use crate::msg::{OracleQueryMsg, PriceResponse};
+
+/// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
+/// this uses our CustomQuerier.
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier: WasmMockQuerier =
+        WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+
+    OwnedDeps {
+        api: MockApi::default(),
+        storage: MockStorage::default(),
+        querier: custom_querier,
+        custom_query_type: Default::default(),
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    token_querier: TokenQuerier,
+    // asset -> (price, last updated)
+    oracle_prices: HashMap<String, (Decimal, u64)>,
+    // staker -> bond amount
+    bond_amounts: HashMap<String, Uint128>,
+    // address -> lock position
+    lock_infos: HashMap<String, LockInfo>,
+    // address -> (attestation, valid)
+    attestations: HashMap<String, (Attestation, bool)>,
+    // collection -> token id -> owner
+    nft_owners: HashMap<String, HashMap<String, String>>,
+    // address -> block in vesting
+    vesting_blocks: HashMap<String, VestingBlockInfo>,
+}
+
+#[derive(Clone, Default)]
+pub struct TokenQuerier {
+    // token contract -> decimals
+    decimals: HashMap<String, u8>,
+    // token contract -> account -> balance
+    balances: HashMap<String, HashMap<String, Uint128>>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        // MockQuerier doesn't support Custom, so we ignore it completely here
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Ok(OracleQueryMsg::Price { asset }) = from_binary(msg) {
+                    let (price, last_updated) = match self.oracle_prices.get(&asset) {
+                        Some(price) => *price,
+                        None => {
+                            return SystemResult::Err(SystemError::InvalidRequest {
+                                error: format!("No price for {}", asset),
+                                request: msg.clone(),
+                            })
+                        }
+                    };
+                    return SystemResult::Ok(ContractResult::from(to_binary(&PriceResponse {
+                        price,
+                        last_updated,
+                    })));
+                }
+                if let Ok(StakingQueryMsg::StakerInfo { staker, .. }) = from_binary(msg) {
+                    let bond_amount = self.bond_amounts.get(&staker).cloned().unwrap_or_default();
+                    return SystemResult::Ok(ContractResult::from(to_binary(&StakerInfoResponse {
+                        staker,
+                        reward_index: Decimal::zero(),
+                        bond_amount,
+                        pending_reward: Uint128::zero(),
+                    })));
+                }
+                if let Ok(LockingQueryMsg::LockInfo { address }) = from_binary(msg) {
+                    let info = self.lock_infos.get(&address).cloned().unwrap_or(LockInfo {
+                        amount: Uint128::zero(),
+                        unlock_started: 0,
+                        locked_at: 0,
+                    });
+                    return SystemResult::Ok(ContractResult::from(to_binary(&LockInfoResponse {
+                        address,
+                        info,
+                        penalty: Uint128::zero(),
+                    })));
+                }
+                if let Ok(AttestationQueryMsg::Attestation { address }) = from_binary(msg) {
+                    let (attestation, valid) = match self.attestations.get(&address) {
+                        Some((attestation, valid)) => (Some(attestation.clone()), *valid),
+                        None => (None, false),
+                    };
+                    return SystemResult::Ok(ContractResult::from(to_binary(&AttestationResponse {
+                        address,
+                        attestation,
+                        valid,
+                    })));
+                }
+                if let Ok(VestingQueryMsg::Blocked { address }) = from_binary(msg) {
+                    return SystemResult::Ok(ContractResult::from(to_binary(&VestingBlockedResponse {
+                        block: self.vesting_blocks.get(&address).cloned(),
+                    })));
+                }
+                match from_binary(msg) {
+                    Ok(Cw721QueryMsg::OwnerOf { token_id, .. }) => {
+                        let owner = match self.nft_owners.get(contract_addr).and_then(|owners| owners.get(&token_id)) {
+                            Some(owner) => owner.clone(),
+                            None => {
+                                return SystemResult::Err(SystemError::InvalidRequest {
+                                    error: format!("No token {}", token_id),
+                                    request: msg.clone(),
+                                })
+                            }
+                        };
+                        return SystemResult::Ok(ContractResult::from(to_binary(&OwnerOfResponse {
+                            owner,
+                            approvals: vec![],
+                        })));
+                    }
+                    Ok(Cw721QueryMsg::Tokens { owner, limit, .. }) => {
+                        let mut tokens: Vec<String> = self.nft_owners.get(contract_addr)
+                            .map(|owners| owners.iter().filter(|(_, o)| **o == owner).map(|(id, _)| id.clone()).collect())
+                            .unwrap_or_default();
+                        tokens.sort();
+                        tokens.truncate(limit.unwrap_or(10) as usize);
+                        return SystemResult::Ok(ContractResult::from(to_binary(&TokensResponse { tokens })));
+                    }
+                    _ => {}
+                }
+                match from_binary(msg) {
+                    Ok(Cw20QueryMsg::TokenInfo {}) => {
+                        let decimals = match self.token_querier.decimals.get(contract_addr) {
+                            Some(decimals) => *decimals,
+                            None => {
+                                return SystemResult::Err(SystemError::NoSuchContract {
+                                    addr: contract_addr.clone(),
+                                })
+                            }
+                        };
+                        SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                            name: "token".to_string(),
+                            symbol: "TOKEN".to_string(),
+                            decimals,
+                            total_supply: Uint128::zero(),
+                        })))
+                    }
+                    Ok(Cw20QueryMsg::Balance { address }) => {
+                        let balance = self
+                            .token_querier
+                            .balances
+                            .get(contract_addr)
+                            .and_then(|balances| balances.get(&address))
+                            .cloned()
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                            balance,
+                        })))
+                    }
+                    _ => panic!("query not mocked"),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            token_querier: TokenQuerier::default(),
+            oracle_prices: HashMap::new(),
+            bond_amounts: HashMap::new(),
+            lock_infos: HashMap::new(),
+            attestations: HashMap::new(),
+            nft_owners: HashMap::new(),
+            vesting_blocks: HashMap::new(),
+        }
+    }
+
+    pub fn with_balance(&mut self, addr: &str, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+
+    pub fn with_token_decimals(&mut self, token: &str, decimals: u8) {
+        self.token_querier.decimals.insert(token.to_string(), decimals);
+    }
+
+    pub fn with_token_balance(&mut self, token: &str, account: &str, balance: Uint128) {
+        self.token_querier
+            .balances
+            .entry(token.to_string())
+            .or_default()
+            .insert(account.to_string(), balance);
+    }
+
+    pub fn with_oracle_price(&mut self, asset: &str, price: Decimal, last_updated: u64) {
+        self.oracle_prices.insert(asset.to_string(), (price, last_updated));
+    }
+
+    pub fn with_bond_amount(&mut self, staker: &str, bond_amount: Uint128) {
+        self.bond_amounts.insert(staker.to_string(), bond_amount);
+    }
+
+    pub fn with_lock_info(&mut self, address: &str, lock_info: LockInfo) {
+        self.lock_infos.insert(address.to_string(), lock_info);
+    }
+
+    pub fn with_nft_owner(&mut self, collection: &str, token_id: &str, owner: &str) {
+        self.nft_owners
+            .entry(collection.to_string())
+            .or_default()
+            .insert(token_id.to_string(), owner.to_string());
+    }
+
+    pub fn with_attestation(&mut self, address: &str, jurisdiction: &str, valid: bool) {
+        let attestation = Attestation { issuer: "issuer".to_string(), jurisdiction: jurisdiction.to_string(), expiry: 0 };
+        self.attestations.insert(address.to_string(), (attestation, valid));
+    }
+
+    pub fn with_vesting_block(&mut self, address: &str, reason: u32) {
+        self.vesting_blocks.insert(address.to_string(), VestingBlockInfo { reason, blocked_at: 0 });
+    }
+}
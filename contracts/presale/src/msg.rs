@@ -1,22 +1,177 @@
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Binary, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{Participant, AlloInfo};
+use crate::state::{BlockInfo, Participant, AlloInfo, Bid, SaleOutcome, SaleMode, FundAsset, Lottery, PricingCurve, PublicWhitelist, WhitelistMode, WhitelistSigner, Round, RoundKind, TicketSource, Tier};
+use crate::types::AssetInfo;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub fund_denom: String,
+    pub fund_assets: Vec<FundAssetMsg>,
     pub reward_token: String,
     pub vesting: String,
     pub whitelist_merkle_root: String,
+    /// Legacy whitelist leaves are accepted until this time, zero to accept only the current format.
+    pub legacy_whitelist_until: u64,
+    pub public_whitelist: PublicWhitelist,
+    pub whitelist_mode: WhitelistMode,
+    pub whitelist_signer: Option<WhitelistSigner>,
 
+    pub sale_mode: SaleMode,
+    pub rounds: Vec<RoundMsg>,
+
+    pub total_rewards_amount: Uint128,
+    pub soft_cap: Uint128,
+    pub oracle: Option<OracleInfo>,
+    pub staking: Option<StakingInfo>,
+    pub locking: Option<LockingInfo>,
+    pub lottery: Option<LotteryInfo>,
+    pub attestation: Option<AttestationInfo>,
+    pub nft_gate: Option<NftGateInfo>,
+    pub withdrawal_penalty: Option<WithdrawalPenaltyInfo>,
+    pub finalize: Option<FinalizeInfo>,
+    pub guardian: Option<String>,
+    /// Extend rounds by the paused duration on unpause.
+    pub extend_on_pause: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundAssetMsg {
+    pub info: AssetInfo,
+    /// Fund amount per reward token * ACCURACY.
     pub exchange_rate: Uint128,
-    pub private_start_time: u64,
-    pub public_start_time: u64,
-    pub presale_period: u64,
+    /// Queried from the token contract, or 6 for native coins, when omitted.
+    pub decimals: Option<u32>,
+    /// Max amount of this asset to be raised, zero for no limit.
+    pub cap: Uint128,
+}
 
-    pub total_rewards_amount: Uint128
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundMsg {
+    pub kind: RoundKind,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// Reward token price in fund value * ACCURACY, defaults to the first fund asset rate or the oracle token price.
+    pub exchange_rate: Option<Uint128>,
+    /// Optional bonding curve starting from the exchange rate.
+    pub curve: Option<PricingCurve>,
+    /// Hex-encoded merkle root, defaults to the presale root.
+    pub merkle_root: Option<String>,
+    /// Max reward token amount sold in this round, zero for no limit.
+    pub cap: Uint128,
+    /// Max fund value per wallet in this round, zero for no limit.
+    pub wallet_cap: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleInfo {
+    /// Price oracle contract, queried with `OracleQueryMsg`.
+    pub contract: String,
+    /// Reward token price in USD * ACCURACY.
+    pub token_price: Uint128,
+    /// Max age of oracle prices in seconds.
+    pub max_price_age: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingInfo {
+    /// Staking contract, queried with `StakerInfo`.
+    pub contract: String,
+    /// Stakes are registered until this time.
+    pub snapshot_time: u64,
+    /// Fund value allocated by a multiplier of one.
+    pub base_allocation: Uint128,
+    /// Tiers ordered by min bond amount, the highest tier reached applies.
+    pub tiers: Vec<Tier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockingInfo {
+    /// Locking contract, queried with `LockInfo`.
+    pub contract: String,
+    /// Fund value allocated per locked token.
+    pub allocation_rate: Decimal,
+    /// Min lock age in seconds.
+    pub min_lock_age: u64,
+    /// Allocation multiplier reached after the boost period, at least one.
+    pub max_boost: Decimal,
+    /// Lock age in seconds over which the multiplier grows to max boost.
+    pub boost_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistEntry {
+    pub address: String,
+    pub allo_info: AlloInfo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftGateInfo {
+    /// cw721 collection, queried with `OwnerOf` and `Tokens`.
+    pub collection: String,
+    /// Fund value allocated per claimed token.
+    pub allocation_per_token: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalPenaltyInfo {
+    /// Treasury receiving the penalties.
+    pub treasury: String,
+    /// Penalty rates at the sale start and end, at most one, the rate moves linearly in between.
+    pub start_rate: Decimal,
+    pub end_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FinalizeInfo {
+    /// Proceeds receivers, shares must sum to one.
+    pub proceeds_receivers: Vec<ProceedsReceiverInfo>,
+    /// Receiver of the reward tokens not sold, or of all of them when the sale failed.
+    pub unsold_receiver: String,
+    /// Vesting start time after the sale end in seconds, not earlier than finalization.
+    pub vesting_start_offset: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProceedsReceiverInfo {
+    pub address: String,
+    pub share: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AttestationInfo {
+    /// Attestation registry, queried with `Attestation`.
+    pub registry: String,
+    /// ISO 3166-1 alpha-2 jurisdiction codes not allowed to deposit.
+    pub blocked_jurisdictions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LotteryInfo {
+    pub ticket_source: TicketSource,
+    /// Balance per ticket.
+    pub ticket_size: Uint128,
+    /// Number of winners drawn.
+    pub winners: u64,
+    /// Fund value each winner may deposit, zero for no limit.
+    pub allocation: Uint128,
+    /// Registration closes at this time, winners are drawn from the first beacon emitted later.
+    pub registration_end: u64,
+    /// Hex-encoded G1 public key of the chained drand network.
+    pub drand_public_key: String,
+    pub drand_genesis_time: u64,
+    pub drand_period: u64,
+}
+
+/// Allocation signed by the whitelist signer, used once in place of a merkle proof.
+/// The signed message is sha256 of the presale contract, round id, sender, allocations, expiry and nonce,
+/// encoded like whitelist leaves under the "presale/permit/v1" domain.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistPermit {
+    pub expiry: u64,
+    pub nonce: u64,
+    pub signature: Binary,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -32,18 +187,102 @@ pub enum ExecuteMsg {
         /// MerkleRoot is hex-encoded merkle root.
         merkle_root: String,
     },
-    UpdatePresaleInfo {
-        new_private_start_time: u64,
-        new_public_start_time: u64,
-        new_presale_period: u64
+    /// Switch the whitelist mode of public rounds.
+    SetPublicWhitelist {
+        public_whitelist: PublicWhitelist,
+    },
+    /// Rotate or remove the whitelist permit signer.
+    SetWhitelistSigner {
+        signer: Option<WhitelistSigner>,
+    },
+    /// Switch between merkle and on-chain whitelist.
+    SetWhitelistMode {
+        whitelist_mode: WhitelistMode,
+    },
+    /// Add or update, then remove on-chain whitelist entries, at most 500 in total.
+    UpdateWhitelist {
+        add: Vec<WhitelistEntry>,
+        remove: Vec<String>,
+    },
+    /// Set or remove the guardian address, owner only.
+    SetGuardian {
+        guardian: Option<String>,
+    },
+    /// Halt deposits and withdrawals, guardian only.
+    Pause {},
+    /// Resume deposits and withdrawals, guardian only.
+    Unpause {},
+    /// Set or remove the compliance address, owner only.
+    SetCompliance {
+        compliance: Option<String>,
+    },
+    /// Block the address from depositing, claiming and refunds, owner or compliance only.
+    Block {
+        address: String,
+        reason: u32,
+    },
+    /// Lift the block of the address, owner or compliance only.
+    Unblock {
+        address: String,
+        reason: u32,
+    },
+    /// Send the refund owed to a blocked address to the recovery address, owner only. In a succeeded escrowed sale
+    /// that is the unspent escrow, with the rewards kept from vesting once finalized.
+    RecoverFrozen {
+        address: String,
+        recovery: String,
+    },
+    /// Require a valid attestation to deposit, or lift the requirement.
+    SetAttestation {
+        attestation: Option<AttestationInfo>,
     },
+    /// Allow withdrawing contributions during the sale with a penalty, or disable withdrawals. Owner only.
+    SetWithdrawalPenalty {
+        withdrawal_penalty: Option<WithdrawalPenaltyInfo>,
+    },
+    /// Set or remove the receivers and vesting offset used by `Finalize`, owner only.
+    SetFinalizeConfig {
+        finalize: Option<FinalizeInfo>,
+    },
+    /// Reschedule or reconfigure a round which has not ended yet.
+    UpdateRound {
+        round_id: u64,
+        round: RoundMsg,
+    },
+    /// Deposit into the active round.
     Deposit {
         allo_info: AlloInfo,
         proof: Vec<String>,
+        #[serde(default)]
+        permit: Option<WhitelistPermit>,
+    },
+    /// Register sender's bond amount in the staking contract before the snapshot time.
+    RegisterStake {},
+    /// Enter the lottery with tickets counted from sender's balance, winners must still hold it to deposit.
+    RegisterLottery {},
+    /// Claim the allocation of sender's tokens in the gating collection, each token is claimed once.
+    /// Sender's first tokens returned by `Tokens` are claimed when no ids are given.
+    ClaimTokenAllocation {
+        token_ids: Option<Vec<String>>,
     },
+    /// Draw the lottery winners from a drand beacon emitted after the registration, callable by anyone.
+    SubmitBeacon {
+        round: u64,
+        /// Hex-encoded signatures of the previous and this round.
+        previous_signature: String,
+        signature: String,
+    },
+    /// Deposit into the active round, which must be private.
     DepositPrivateSale {
         allo_info: AlloInfo,
         proof: Vec<String>,
+        #[serde(default)]
+        permit: Option<WhitelistPermit>,
+    },
+    /// Withdraw fund value from sender's contribution before the sale ends, the latest rounds first.
+    /// The penalty is sent to the treasury and the rewards bought are given up.
+    WithdrawContribution {
+        amount: Uint128,
     },
     WithdrawFunds {
         receiver: String,
@@ -51,6 +290,57 @@ pub enum ExecuteMsg {
     WithdrawUnsoldToken {
         receiver: String,
     },
+    /// Cancel the sale before the proceeds are withdrawn and return the reward tokens to the owner.
+    CancelSale {},
+    /// Close the presale once the sale ended, callable by anyone: settle, send the proceeds and unsold tokens,
+    /// start vesting and lock the presale. Escrowed allocations must be registered first.
+    Finalize {},
+    /// Refund participant's funds when the presale missed the soft cap or was cancelled.
+    ClaimRefund {},
+    /// Settle participant's escrowed bid at the clearing price, refund the difference and update vesting.
+    Claim {},
+    /// Register the next escrowed allocations not claimed yet in vesting once the sale succeeded, callable by anyone.
+    /// Participants blocked in vesting are skipped.
+    RegisterAllocations {
+        limit: Option<u32>,
+    },
+    /// Commit a sealed bid with escrowed funds.
+    CommitBid {
+        /// Hex-encoded sha256(contract || bidder || price || amount || salt), contract and bidder
+        /// prefixed by their 4 byte big-endian length, numbers as 16 byte big-endian, salt as raw bytes.
+        commitment: String,
+    },
+    RevealBid {
+        price: Uint128,
+        amount: Uint128,
+        salt: String,
+    },
+    /// Fill revealed bids once the reveal period is over.
+    Settle {},
+    /// Deposit with CW20 fund token
+    Receive(Cw20ReceiveMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    Deposit {
+        allo_info: AlloInfo,
+        proof: Vec<String>,
+        #[serde(default)]
+        permit: Option<WhitelistPermit>,
+    },
+    DepositPrivateSale {
+        allo_info: AlloInfo,
+        proof: Vec<String>,
+        #[serde(default)]
+        permit: Option<WhitelistPermit>,
+    },
+    CommitBid {
+        commitment: String,
+    },
+    /// Deposit the reward tokens for sale, the sold amount is sent to vesting on finalize.
+    FundRewards {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -65,7 +355,47 @@ pub enum QueryMsg {
     GetParticipant {
         user: String,
     },
-    PresaleInfo {}
+    PresaleInfo {},
+    /// Round active at the block time, if any.
+    CurrentRound {},
+    /// Rounds which have not started yet.
+    UpcomingRounds {},
+    GetBid {
+        user: String,
+    },
+    /// On-chain whitelist entries ordered by address, with remaining allocations.
+    Whitelist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Registered stake and guaranteed allocation of the user.
+    StakingTier {
+        user: String,
+    },
+    /// Lock position and boosted allocation of the user.
+    LockAllocation {
+        user: String,
+    },
+    /// Lottery tickets of the user and whether the user won.
+    LotteryEntry {
+        user: String,
+    },
+    /// Allocation claimed by the user's tokens and the part left.
+    TokenAllocation {
+        user: String,
+    },
+    Blocked {
+        address: String,
+    },
+    /// Holder which claimed the allocation of the token, if any.
+    UsedToken {
+        token_id: String,
+    },
+    /// Reward amount the contribution buys in the active round.
+    SimulateDeposit {
+        asset: AssetInfo,
+        amount: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -73,6 +403,21 @@ pub struct ParticipantsCountResponse {
     pub count: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistResponse {
+    pub entries: Vec<WhitelistEntryResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistEntryResponse {
+    pub address: String,
+    pub allo_info: AlloInfo,
+    // Private allocation left after private purchases
+    pub private_remaining: Uint128,
+    // Public allocation left after public purchases
+    pub public_remaining: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct GetParticipantsResponse {
     pub participants: Vec<String>,
@@ -86,23 +431,148 @@ pub struct GetParticipantResponse {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct GetSaleStatusResponse {
     pub private_sold_amount: Uint128,
-    pub public_sold_amount: Uint128
+    pub public_sold_amount: Uint128,
+    pub total_raised: Uint128,
+    pub escrowed_rewards: Uint128,
+    pub outcome: SaleOutcome,
+    pub clearing_price: Option<Uint128>,
+    pub fill_ratio: Option<Decimal>,
+    pub fund_assets: Vec<FundAsset>,
+    pub finalized: bool,
+    pub registered_participants: u64,
+    pub frozen_rewards: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PresaleInfoResponse {
     // owner
     pub owner: String,
+    // Accepted fund assets with exchange rates
+    pub fund_assets: Vec<FundAsset>,
+    // Legacy whitelist leaves are accepted until this time
+    pub legacy_whitelist_until: u64,
+    // Whitelist mode of public rounds
+    pub public_whitelist: PublicWhitelist,
+    // Source of whitelisted allocations
+    pub whitelist_mode: WhitelistMode,
+    // Whitelist permit signer
+    pub whitelist_signer: Option<WhitelistSigner>,
+    // Pricing mode
+    pub sale_mode: SaleMode,
     // Accuracy
     pub accuracy: Uint128,
-    // Exchange rate
-    pub exchange_rate: Uint128,
-    // Presale Period.
-    pub presale_period: u64,
-    // Public Presale Start Time.
-    pub public_start_time: u64,
-    // Private Presale Start Time.
-    pub private_start_time: u64,
+    // Sale rounds
+    pub rounds: Vec<Round>,
     // Accuracy
     pub total_rewards_amount: Uint128,
+    // Minimum fund value to be raised.
+    pub soft_cap: Uint128,
+    // USD price oracle
+    pub oracle: Option<OracleInfo>,
+    // Staking tiers
+    pub staking: Option<StakingInfo>,
+    // Lock position weights
+    pub locking: Option<LockingInfo>,
+    // Allocation lottery
+    pub lottery: Option<Lottery>,
+    // Attestation gating
+    pub attestation: Option<AttestationInfo>,
+    // NFT holder gating
+    pub nft_gate: Option<NftGateInfo>,
+    // Penalty on withdrawn contributions
+    pub withdrawal_penalty: Option<WithdrawalPenaltyInfo>,
+    // Finalize receivers and vesting offset
+    pub finalize: Option<FinalizeInfo>,
+    // Blocklist manager besides the owner
+    pub compliance: Option<String>,
+    // Emergency pause
+    pub guardian: Option<String>,
+    pub paused: bool,
+    pub paused_at: Option<u64>,
+    pub extend_on_pause: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundResponse {
+    pub round: Option<Round>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundsResponse {
+    pub rounds: Vec<Round>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GetBidResponse {
+    pub bid: Option<Bid>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateDepositResponse {
+    pub round_id: u64,
+    pub reward_amount: Uint128,
+    // Fund amount accepted by the round, the rest would be refunded
+    pub accepted_amount: Uint128,
+    pub refund_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingTierResponse {
+    // Bond amount registered before the snapshot time
+    pub snapshot_bond: Uint128,
+    // Bond amount counted for the tier, capped by the current bond
+    pub bond_amount: Uint128,
+    // Index of the tier reached
+    pub tier: Option<u64>,
+    // Guaranteed allocation in fund value
+    pub allocation: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockAllocationResponse {
+    pub locked_amount: Uint128,
+    // Seconds since the tokens were locked
+    pub lock_age: u64,
+    // Position is not being unlocked and old enough
+    pub eligible: bool,
+    // Boosted allocation in fund value
+    pub allocation: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LotteryEntryResponse {
+    pub tickets: Uint128,
+    pub winner: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenAllocationResponse {
+    pub allocation: Uint128,
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockedResponse {
+    pub block: Option<BlockInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsedTokenResponse {
+    pub claimed_by: Option<String>,
+}
+
+/// Query interface of the USD price oracle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    /// USD price of one whole unit of the asset (denom or token contract address).
+    Price {
+        asset: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Decimal,
+    pub last_updated: u64,
 }
\ No newline at end of file
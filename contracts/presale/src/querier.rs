@@ -3,6 +3,15 @@ use cosmwasm_std::{
     StdResult, WasmQuery, Uint128,
 };
 use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw721::{Cw721QueryMsg, OwnerOfResponse, TokensResponse};
+
+use attestation::msg::{AttestationResponse, QueryMsg as AttestationQueryMsg};
+use locking::msg::{LockInfoResponse, QueryMsg as LockingQueryMsg};
+use locking::state::LockInfo;
+use staking::msg::{QueryMsg as StakingQueryMsg, StakerInfoResponse};
+use vesting::msg::{BlockedResponse as VestingBlockedResponse, QueryMsg as VestingQueryMsg};
+
+use crate::msg::{OracleQueryMsg, PriceResponse};
 
 pub fn query_all_balances(deps: Deps, account_addr: Addr) -> StdResult<Vec<Coin>> {
     // load price form the oracle
@@ -61,3 +70,64 @@ pub fn query_supply(deps: Deps, contract_addr: Addr) -> StdResult<Uint128> {
 
     Ok(Uint128::from(token_info.total_supply.u128()))
 }
+
+pub fn query_price(deps: Deps, oracle_addr: Addr, asset: String) -> StdResult<PriceResponse> {
+    // load price form the oracle
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_addr.to_string(),
+        msg: to_binary(&OracleQueryMsg::Price { asset })?,
+    }))
+}
+
+pub fn query_bond_amount(deps: Deps, staking_addr: Addr, staker: String) -> StdResult<Uint128> {
+    // load bond amount from the staking contract
+    let res: StakerInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: staking_addr.to_string(),
+        msg: to_binary(&StakingQueryMsg::StakerInfo { staker, block_time: None })?,
+    }))?;
+    Ok(res.bond_amount)
+}
+
+pub fn query_lock_info(deps: Deps, locking_addr: Addr, address: String) -> StdResult<LockInfo> {
+    // load lock position from the locking contract
+    let res: LockInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: locking_addr.to_string(),
+        msg: to_binary(&LockingQueryMsg::LockInfo { address })?,
+    }))?;
+    Ok(res.info)
+}
+
+pub fn query_attestation(deps: Deps, registry_addr: Addr, address: String) -> StdResult<AttestationResponse> {
+    // load attestation from the registry
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: registry_addr.to_string(),
+        msg: to_binary(&AttestationQueryMsg::Attestation { address })?,
+    }))
+}
+
+pub fn query_vesting_blocked(deps: Deps, vesting_addr: Addr, address: String) -> StdResult<bool> {
+    // load block from the vesting contract
+    let res: VestingBlockedResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: vesting_addr.to_string(),
+        msg: to_binary(&VestingQueryMsg::Blocked { address })?,
+    }))?;
+    Ok(res.block.is_some())
+}
+
+pub fn query_nft_owner(deps: Deps, collection_addr: Addr, token_id: String) -> StdResult<String> {
+    // load token owner from the cw721 collection
+    let res: OwnerOfResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: collection_addr.to_string(),
+        msg: to_binary(&Cw721QueryMsg::OwnerOf { token_id, include_expired: None })?,
+    }))?;
+    Ok(res.owner)
+}
+
+pub fn query_nft_tokens(deps: Deps, collection_addr: Addr, owner: String, limit: Option<u32>) -> StdResult<Vec<String>> {
+    // load owned token ids from the cw721 collection
+    let res: TokensResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: collection_addr.to_string(),
+        msg: to_binary(&Cw721QueryMsg::Tokens { owner, start_after: None, limit })?,
+    }))?;
+    Ok(res.tokens)
+}
@@ -2,8 +2,10 @@ use cosmwasm_storage::{singleton, singleton_read};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, Uint128, Storage, StdResult};
-use cw_storage_plus::{Map};
+use cosmwasm_std::{Binary, CanonicalAddr, Decimal, Uint128, Order, Storage, StdResult};
+use cw_storage_plus::{Bound, Map};
+
+use crate::types::AssetInfo;
 
 const KEY_STATE: &[u8] = b"state";
 
@@ -13,34 +15,322 @@ pub struct State {
     pub owner: CanonicalAddr,
 
     /************** Address Infos *************/
-    // Token for fundraise.
-    pub fund_denom: String,
+    // Native coins or CW20 tokens accepted for fundraise.
+    pub fund_assets: Vec<FundAsset>,
     // Token for distribution.
     pub reward_token: CanonicalAddr,
     // Vesting Contract.
     pub vesting: CanonicalAddr,
-    // Whitelist Merkle Root.
+    // Whitelist Merkle Root, used by rounds without their own root.
     pub whitelist_merkle_root: String,
+    // Whitelist leaves of the legacy format are accepted until this time.
+    pub legacy_whitelist_until: u64,
+    // Whitelist mode of public rounds.
+    pub public_whitelist: PublicWhitelist,
+    // Source of whitelisted allocations.
+    pub whitelist_mode: WhitelistMode,
+    // Key signing whitelist permits, accepted in place of merkle proofs.
+    pub whitelist_signer: Option<WhitelistSigner>,
+    // USD price oracle, contributions are valued in USD when set.
+    pub oracle: Option<OracleConfig>,
+    // Staking contract tiers granting guaranteed private allocations.
+    pub staking: Option<StakingTiers>,
+    // Locking contract positions granting boosted private allocations.
+    pub locking: Option<LockingWeights>,
+    // Lottery awarding deposit rights with drand randomness.
+    pub lottery: Option<Lottery>,
+    // Attestation registry gating deposits.
+    pub attestation: Option<AttestationGate>,
+    // Compliance address, manages the blocklist with the owner.
+    pub compliance: Option<CanonicalAddr>,
+    // Guardian address, pauses deposits and withdrawals in emergencies.
+    pub guardian: Option<CanonicalAddr>,
+    // Pause start, set while deposits and withdrawals are paused.
+    pub paused_at: Option<u64>,
+    // Rounds not ended when paused are extended by the paused duration.
+    pub extend_on_pause: bool,
+    // cw721 collection whose holders are granted allocations.
+    pub nft_gate: Option<NftGate>,
+    // Penalty on contributions withdrawn during the sale, withdrawals are disabled when unset.
+    pub withdrawal_penalty: Option<WithdrawalPenalty>,
+    // Receivers and vesting schedule applied by `Finalize`.
+    pub finalize: Option<FinalizeConfig>,
 
     /************** Presale Params *************/
-    // Fixed rate between fundToken vs rewardToken = reward / fund * ACCURACY.
-    pub exchange_rate: Uint128,
-    // Presale Period.
-    pub presale_period: u64,
-    // Public Presale Start Time.
-    pub public_start_time: u64,
-    // Private Presale Start Time.
-    pub private_start_time: u64,
+    // Pricing mode of the sale.
+    pub sale_mode: SaleMode,
+    // Sale rounds ordered by time.
+    pub rounds: Vec<Round>,
     // Total reward token amount
     pub total_rewards_amount: Uint128,
+    // Minimum fund value (USD in oracle mode) to be raised, otherwise the sale fails and participants are refunded.
+    pub soft_cap: Uint128,
 
     /************** Status Info *************/
     // Reward token amount sold by private sale
     pub private_sold_amount: Uint128,
     // Reward token amount sold by public sale
     pub public_sold_amount: Uint128,
+    // Fund value raised by private and public sale
+    pub total_raised: Uint128,
+    // Reward tokens deposited with `FundRewards`, held until sent to vesting on finalize.
+    pub escrowed_rewards: Uint128,
+    // Uniform price paid by auction bidders, set when the supply sells out or on settlement.
+    pub clearing_price: Option<Uint128>,
+    // Share of the demand filled at the clearing price, set on settlement.
+    pub fill_ratio: Option<Decimal>,
+    // Proceeds have been withdrawn by the owner.
+    pub proceeds_withdrawn: bool,
+    // Sale cancelled by the owner, participants reclaim their funds.
+    pub cancelled: bool,
+    // Presale finalized, only participant settlement and compliance controls are accepted.
+    pub finalized: bool,
     // Participants address list
     pub userlist: Vec<String>,
+    // Participants of escrowed sales registered in vesting, in userlist order.
+    pub registered_participants: u64,
+    // Escrowed rewards of participants blocked in vesting, kept here on finalize until recovered.
+    pub frozen_rewards: Uint128,
+    // Fund value of staking tier allocations registered and not yet deposited, reserved until the private rounds end.
+    pub reserved_allocations: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundAsset {
+    // Native coin or CW20 token.
+    pub info: AssetInfo,
+    // Fixed rate between this asset vs rewardToken = fund / reward * ACCURACY.
+    pub exchange_rate: Uint128,
+    // Decimals of this asset.
+    pub decimals: u32,
+    // Max amount of this asset to be raised, zero for no limit.
+    pub cap: Uint128,
+    // Amount of this asset raised by private sale
+    pub private_raised: Uint128,
+    // Amount of this asset raised by public sale
+    pub public_raised: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleMode {
+    // Fixed price per round, rewards are registered for vesting on deposit.
+    FixedPrice,
+    // Price decays linearly from start price to floor price over the sale. Bids are escrowed,
+    // every bidder pays the clearing price and rewards are registered for vesting on claim.
+    DutchAuction { start_price: Uint128, floor_price: Uint128 },
+    // Sealed bids are committed during the rounds and revealed for reveal period after the sale.
+    // Settlement fills bids from the highest price down, winners pay the clearing price on claim.
+    SealedBid { reserve_price: Uint128, reveal_period: u64 },
+    // Any amount is escrowed during the rounds. Settlement after the sale fills contributions pro rata
+    // when they exceed the target raise in fund value, rewards and refunds are computed on claim.
+    FairLaunch { target_raise: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundKind {
+    // Whitelisted round, purchases are limited by the private allocation.
+    Private,
+    // Public round.
+    Public,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicWhitelist {
+    // Anyone can deposit without an allocation limit.
+    Open,
+    // Proofs are verified against the private whitelist root, purchases are limited by the public allocation.
+    SharedRoot,
+    // Proofs are verified against a separate public root, purchases are limited by the public allocation.
+    SeparateRoot { merkle_root: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WhitelistMode {
+    // Allocations are claimed with merkle proofs or signed permits.
+    Merkle,
+    // Allocations are read from the owner-managed whitelist, proofs are ignored.
+    OnChain,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+    Secp256k1,
+    Ed25519,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistSigner {
+    pub scheme: SignatureScheme,
+    // Compressed or uncompressed secp256k1 key, or ed25519 key.
+    pub public_key: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Round {
+    // Round id, position in the schedule starting from 1.
+    pub id: u64,
+    pub kind: RoundKind,
+    // Round is active from start time until end time.
+    pub start_time: u64,
+    pub end_time: u64,
+    // Reward token price = fund value / reward * ACCURACY, defaults to the first fund asset rate or the oracle token price.
+    pub exchange_rate: Option<Uint128>,
+    // Price rises along the curve with the amount sold in this round, starting from the exchange rate.
+    pub curve: Option<PricingCurve>,
+    // Whitelist Merkle Root of this round, defaults to the presale root.
+    pub merkle_root: Option<String>,
+    // Max reward token amount sold in this round, zero for no limit.
+    pub cap: Uint128,
+    // Max fund value per wallet in this round, zero for no limit.
+    pub wallet_cap: Uint128,
+    // Reward token amount sold in this round
+    pub sold_amount: Uint128,
+    // Fund value raised in this round
+    pub raised: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingCurve {
+    // Price rises by slope for every whole reward token sold.
+    Linear { slope: Uint128 },
+    // Price is multiplied by growth for every step of reward token amount sold.
+    Exponential { growth: Decimal, step: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleConfig {
+    // Price oracle contract.
+    pub contract: CanonicalAddr,
+    // Fixed reward token price = USD / reward * ACCURACY.
+    pub token_price: Uint128,
+    // Oracle prices older than this are rejected, in seconds.
+    pub max_price_age: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingTiers {
+    // Staking contract, queried for bond amounts.
+    pub contract: CanonicalAddr,
+    // Stakes are registered until this time, stakes added later do not count.
+    pub snapshot_time: u64,
+    // Fund value allocated by a multiplier of one.
+    pub base_allocation: Uint128,
+    // Tiers ordered by min bond amount.
+    pub tiers: Vec<Tier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Tier {
+    // Min staked amount to reach this tier.
+    pub min_bond: Uint128,
+    // Allocation multiplier of this tier.
+    pub multiplier: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockingWeights {
+    // Locking contract, queried for lock positions.
+    pub contract: CanonicalAddr,
+    // Fund value allocated per locked token.
+    pub allocation_rate: Decimal,
+    // Positions locked for less than this are not eligible, in seconds.
+    pub min_lock_age: u64,
+    // Allocation multiplier reached after the boost period.
+    pub max_boost: Decimal,
+    // Lock age over which the multiplier grows linearly from one to max boost, in seconds.
+    pub boost_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftGate {
+    // cw721 collection, queried with `OwnerOf` and `Tokens`.
+    pub collection: CanonicalAddr,
+    // Fund value allocated per claimed token.
+    pub allocation_per_token: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalPenalty {
+    // Treasury receiving the penalties.
+    pub treasury: CanonicalAddr,
+    // Penalty rate moves linearly from the start rate at the sale start to the end rate at the sale end.
+    pub start_rate: Decimal,
+    pub end_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FinalizeConfig {
+    // Proceeds receivers, shares sum to one.
+    pub proceeds_receivers: Vec<ProceedsReceiver>,
+    // Receiver of the reward tokens not sold.
+    pub unsold_receiver: CanonicalAddr,
+    // Vesting starts this long after the sale end, in seconds.
+    pub vesting_start_offset: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProceedsReceiver {
+    pub address: CanonicalAddr,
+    // Share of the proceeds of every fund asset.
+    pub share: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockInfo {
+    // Reason code given when blocked.
+    pub reason: u32,
+    // Timestamp of the block.
+    pub blocked_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AttestationGate {
+    // Attestation registry, depositors need a valid attestation.
+    pub registry: CanonicalAddr,
+    // Upper case jurisdiction codes not allowed to deposit.
+    pub blocked_jurisdictions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketSource {
+    // Bond amount in the staking contract.
+    Staking { contract: String },
+    // Locked amount in the locking contract, positions being unlocked get no tickets.
+    Locking { contract: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lottery {
+    // Balance tickets are counted from.
+    pub ticket_source: TicketSource,
+    // Balance per ticket.
+    pub ticket_size: Uint128,
+    // Number of winners drawn.
+    pub winners: u64,
+    // Fund value each winner may deposit, zero for no limit.
+    pub allocation: Uint128,
+    // Registration closes at this time, winners are drawn from the first beacon emitted later.
+    pub registration_end: u64,
+    // Hex-encoded G1 public key of the drand chain.
+    pub drand_public_key: String,
+    // Genesis time and round period of the drand chain, in seconds.
+    pub drand_genesis_time: u64,
+    pub drand_period: u64,
+    // Tickets of all entrants
+    pub total_tickets: Uint128,
+    // Number of entrants, capped by MAX_LOTTERY_ENTRANTS
+    pub entrants: u64,
+    // Beacon round the winners were drawn from
+    pub beacon_round: Option<u64>,
+    // Hex-encoded randomness of the beacon
+    pub randomness: Option<String>,
 }
 
 pub fn store_state(storage: &mut dyn Storage, state: &State) -> StdResult<()> {
@@ -51,8 +341,22 @@ pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
     singleton_read::<State>(storage, KEY_STATE).load()
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleOutcome {
+    // Presale has not ended yet.
+    Pending,
+    // Presale ended with soft cap reached.
+    Succeeded,
+    // Presale ended below soft cap, participants can claim refunds.
+    Failed,
+    // Presale cancelled by the owner, participants can claim refunds.
+    Cancelled,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AlloInfo {
+    // Allocations are in fund value, USD with USD_DECIMALS in oracle mode.
     // Max allocation for this user in public presale
     pub public_allocation: Uint128,
     // Max allocation for this user in private presale
@@ -61,14 +365,97 @@ pub struct AlloInfo {
 
 pub const PRIVATE_SOLD_FUNDS: Map<String, Uint128> = Map::new("private_sold_funds");
 
+// (round id, participant) -> fund value deposited in the round
+pub const ROUND_FUNDS: Map<(u64, String), Uint128> = Map::new("round_funds");
+
+// (round id, participant) -> reward token amount bought in the round
+pub const ROUND_REWARDS: Map<(u64, String), Uint128> = Map::new("round_rewards");
+
+// participant -> bond amount registered before the snapshot time
+pub const STAKE_SNAPSHOTS: Map<String, Uint128> = Map::new("stake_snapshots");
+
+// participant -> fund value of the tier allocation reserved on registration
+pub const TIER_RESERVATIONS: Map<String, Uint128> = Map::new("tier_reservations");
+
+// entrant -> lottery tickets
+pub const LOTTERY_TICKETS: Map<String, Uint128> = Map::new("lottery_tickets");
+
+// lottery winner -> drawn
+pub const LOTTERY_WINNERS: Map<String, bool> = Map::new("lottery_winners");
+
+// (participant, nonce) -> whitelist permit used
+pub const USED_PERMITS: Map<(String, u64), bool> = Map::new("used_permits");
+
+// token id -> holder which claimed its allocation
+pub const USED_TOKENS: Map<String, String> = Map::new("used_tokens");
+
+// holder -> fund value allocated by claimed tokens
+pub const TOKEN_ALLOCATIONS: Map<String, Uint128> = Map::new("token_allocations");
+
+// blocked address -> block info
+pub const BLOCKLIST: Map<String, BlockInfo> = Map::new("blocklist");
+
+// address -> allocation of the on-chain whitelist
+pub const WHITELIST: Map<String, AlloInfo> = Map::new("whitelist");
+
+pub const MAX_LIMIT: u32 = 30;
+pub const DEFAULT_LIMIT: u32 = 10;
+pub fn read_whitelist(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, AlloInfo)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    WHITELIST
+        .range(storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
 pub const ACCURACY: u128 = 100000000u128;
 
+// Entrants a lottery accepts, winners are drawn over all of them at once.
+pub const MAX_LOTTERY_ENTRANTS: u64 = 1000;
+
+pub const USD_DECIMALS: u32 = 6;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Participant {
-    // Fund token amount by participant.
+    // Fund value by participant, in units of the first fund asset or USD in oracle mode.
     pub fund_balance: Uint128,
     // Reward token amount need to be vested.
     pub reward_balance: Uint128,
+    // Escrowed bid has been settled.
+    #[serde(default)]
+    pub claimed: bool,
+    // Escrowed rewards have been registered in vesting.
+    #[serde(default)]
+    pub registered: bool,
+    // Escrowed rewards were skipped on registration, blocked in vesting.
+    #[serde(default)]
+    pub frozen: bool,
 }
 
 pub const PARTICIPANTS: Map<String, Participant> = Map::new("participants");
+
+// (participant, fund asset) -> fund asset amount deposited
+pub const PARTICIPANT_FUNDS: Map<(String, String), Uint128> = Map::new("participant_funds");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bid {
+    // Hex-encoded sha256 of (contract, bidder, price, amount, salt).
+    pub commitment: String,
+    // Revealed price = fund value / reward * ACCURACY.
+    pub price: Uint128,
+    // Revealed reward token amount.
+    pub amount: Uint128,
+    pub revealed: bool,
+}
+
+pub const BIDS: Map<String, Bid> = Map::new("bids");
+
+// price -> revealed reward token amount bid at this price
+pub const BID_DEMAND: Map<u128, Uint128> = Map::new("bid_demand");
+
+// fund asset -> escrowed deposits kept as proceeds, accumulated on registration
+pub const ESCROW_PROCEEDS: Map<String, Uint128> = Map::new("escrow_proceeds");
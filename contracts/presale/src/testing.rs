@@ -117,3 +117,1788 @@
 //     let msg = ExecuteMsg::Deposit { allo_info, proof };
 //     execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 // }
+
+use crate::contract::{bid_commitment, execute, instantiate, permit_hash, query};
+use crate::error::ContractError;
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
+use crate::msg::{
+    Cw20HookMsg, ExecuteMsg, FundAssetMsg, GetSaleStatusResponse, InstantiateMsg, OracleInfo, QueryMsg, RoundMsg,
+    RoundResponse, RoundsResponse, SimulateDepositResponse, AttestationInfo, BlockedResponse, NftGateInfo, TokenAllocationResponse, WhitelistEntry, WhitelistResponse, StakingInfo, StakingTierResponse, LockingInfo, LockAllocationResponse, LotteryInfo, LotteryEntryResponse, PresaleInfoResponse,
+    GetParticipantResponse, WhitelistPermit, WithdrawalPenaltyInfo, FinalizeInfo, ProceedsReceiverInfo,
+};
+use crate::state::{AlloInfo, PricingCurve, RoundKind, SaleMode, SaleOutcome, PublicWhitelist, SignatureScheme, WhitelistMode, TicketSource, Tier, WhitelistSigner, MAX_LOTTERY_ENTRANTS};
+use crate::types::AssetInfo;
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    coins, from_binary, to_binary, BankMsg, Binary, Coin, CosmosMsg, Decimal, Env, OwnedDeps, Response, Timestamp, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use sha2::Digest;
+use locking::state::LockInfo;
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+
+const START_TIME: u64 = 1000;
+const PERIOD: u64 = 1000;
+
+// Recorded beacon of the drand mainnet chain
+const DRAND_PUBLIC_KEY: &str = "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31";
+const DRAND_GENESIS_TIME: u64 = 1595431050;
+const BEACON_ROUND: u64 = 72785;
+const BEACON_PREVIOUS_SIGNATURE: &str = "a609e19a03c2fcc559e8dae14900aaefe517cb55c840f6e69bc8e4f66c8d18e8a609685d9917efbfb0c37f058c2de88f13d297c7e19e0ab24813079efe57a182554ff054c7638153f9b26a60e7111f71a0ff63d9571704905d3ca6df0b031747";
+const BEACON_SIGNATURE: &str = "82f5d3d2de4db19d40a6980e8aa37842a0e55d1df06bd68bddc8d60002e8e959eb9cfa368b3c1b77d18f02a54fe047b80f0989315f83b12a74fd8679c4f12aae86eaf6ab5690b34f1fddd50ee3cc6f6cdf59e95526d5a5d82aaa84fa6f181e42";
+const BEACON_RANDOMNESS: &str = "8b676484b5fb1f37f9ec5c413d7d29883504e5b669f604a1ce68b3388e9ae3d9";
+
+fn default_init_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        fund_assets: vec![FundAssetMsg {
+            info: AssetInfo::NativeToken { denom: "uusd".to_string() },
+            exchange_rate: Uint128::from(100000000u128),
+            decimals: None,
+            cap: Uint128::zero(),
+        }],
+        reward_token: "reward_token".to_string(),
+        vesting: "vesting".to_string(),
+        whitelist_merkle_root: "".to_string(),
+        legacy_whitelist_until: 0,
+        public_whitelist: PublicWhitelist::Open,
+        whitelist_mode: WhitelistMode::Merkle,
+        whitelist_signer: None,
+
+        sale_mode: SaleMode::FixedPrice,
+        rounds: vec![public_round(START_TIME, START_TIME + PERIOD)],
+
+        total_rewards_amount: Uint128::from(1000000u128),
+        soft_cap: Uint128::from(500u128),
+        oracle: None,
+        staking: None,
+        locking: None,
+        lottery: None,
+        attestation: None,
+        nft_gate: None,
+        withdrawal_penalty: None,
+        finalize: None,
+        guardian: Some("guardian".to_string()),
+        extend_on_pause: false,
+    }
+}
+
+fn public_round(start_time: u64, end_time: u64) -> RoundMsg {
+    RoundMsg {
+        kind: RoundKind::Public,
+        start_time,
+        end_time,
+        exchange_rate: None,
+        curve: None,
+        merkle_root: None,
+        cap: Uint128::zero(),
+        wallet_cap: Uint128::zero(),
+    }
+}
+
+fn setup(init_msg: InstantiateMsg) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals("reward_token", 6);
+    let total_rewards_amount = init_msg.total_rewards_amount;
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+    fund_rewards(&mut deps, total_rewards_amount).unwrap();
+    deps
+}
+
+fn fund_rewards(deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, amount: Uint128) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "owner".to_string(),
+        amount,
+        msg: to_binary(&Cw20HookMsg::FundRewards {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("reward_token", &[]), msg)
+}
+
+fn env_at(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(seconds);
+    env
+}
+
+fn empty_allocation() -> AlloInfo {
+    AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::zero() }
+}
+
+// Whitelist leaf = sha256(0x00 || domain || contract || round id || user || private allocation || public allocation),
+// strings prefixed with their u32 length
+fn whitelist_leaf(contract: &str, round_id: u64, user: &str, allo_info: &AlloInfo) -> [u8; 32] {
+    let mut data = vec![0u8];
+    for field in [&b"presale/whitelist/v2"[..], contract.as_bytes()] {
+        data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        data.extend_from_slice(field);
+    }
+    data.extend_from_slice(&round_id.to_be_bytes());
+    data.extend_from_slice(&(user.len() as u32).to_be_bytes());
+    data.extend_from_slice(user.as_bytes());
+    data.extend_from_slice(&allo_info.private_allocation.u128().to_be_bytes());
+    data.extend_from_slice(&allo_info.public_allocation.u128().to_be_bytes());
+    sha2::Sha256::digest(&data).into()
+}
+
+// Inner node = sha256(0x01 || sorted children)
+fn whitelist_node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+    let mut children = [left, right];
+    children.sort_unstable();
+    sha2::Sha256::digest([&[1u8][..], &children.concat()].concat()).into()
+}
+
+#[test]
+fn test_refund_when_soft_cap_missed() {
+    let mut deps = setup(default_init_msg());
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), msg).unwrap();
+
+    // refunds are not available while the sale is running
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("user", &[]), ExecuteMsg::ClaimRefund {});
+    assert_eq!(res, Err(ContractError::SaleNotFailed {}));
+
+    let env = env_at(START_TIME + PERIOD + 1);
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env.clone(), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.outcome, SaleOutcome::Failed);
+    assert_eq!(status.total_raised, Uint128::from(100u128));
+
+    // owner can't sweep funds of a failed sale
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::SaleFailed {}));
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::WithdrawUnsoldToken { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::SaleFailed {}));
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), ExecuteMsg::ClaimRefund {}).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(100, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::ResetRecipient { recp: "user".to_string() }).unwrap(),
+        funds: vec![],
+    }));
+
+    // can't claim twice
+    let res = execute(deps.as_mut(), env, mock_info("user", &[]), ExecuteMsg::ClaimRefund {});
+    assert_eq!(res, Err(ContractError::NothingToRefund {}));
+}
+
+#[test]
+fn test_withdraw_when_soft_cap_reached() {
+    let mut deps = setup(default_init_msg());
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(500, "uusd")), msg).unwrap();
+
+    let env = env_at(START_TIME + PERIOD + 1);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), ExecuteMsg::ClaimRefund {});
+    assert_eq!(res, Err(ContractError::SaleNotFailed {}));
+
+    deps.querier.with_balance(MOCK_CONTRACT_ADDR, coins(500, "uusd"));
+    let res = execute(deps.as_mut(), env, mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+}
+
+#[test]
+fn test_fund_rewards() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals("reward_token", 6);
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), default_init_msg()).unwrap();
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::RewardsNotFunded {}));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "owner".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_binary(&Cw20HookMsg::FundRewards {}).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("fund_token", &[]), msg);
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+
+    fund_rewards(&mut deps, Uint128::from(400000u128)).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::RewardsNotFunded {}));
+
+    fund_rewards(&mut deps, Uint128::from(600000u128)).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), deposit).unwrap();
+    let status: GetSaleStatusResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME + 1), QueryMsg::GetSaleStatus {}).unwrap()).unwrap();
+    assert_eq!(status.escrowed_rewards, Uint128::from(1000000u128));
+}
+
+#[test]
+fn test_cancel_sale() {
+    let mut deps = setup(default_init_msg());
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(600, "uusd")), deposit.clone()).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("user", &[]), ExecuteMsg::CancelSale {});
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+    // all escrowed reward tokens are returned to the owner
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("owner", &[]), ExecuteMsg::CancelSale {}).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "owner".to_string(),
+            amount: Uint128::from(1000000u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("owner", &[]), ExecuteMsg::CancelSale {});
+    assert_eq!(res, Err(ContractError::SaleCancelled {}));
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 2), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.outcome, SaleOutcome::Cancelled);
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 3), mock_info("user", &coins(10, "uusd")), deposit);
+    assert_eq!(res, Err(ContractError::SaleCancelled {}));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 3), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::SaleCancelled {}));
+
+    // refunds are available right away, before the sale ends
+    let res = execute(deps.as_mut(), env_at(START_TIME + 3), mock_info("user", &[]), ExecuteMsg::ClaimRefund {}).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(600, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::ResetRecipient { recp: "user".to_string() }).unwrap(),
+        funds: vec![],
+    }));
+}
+
+#[test]
+fn test_cancel_after_withdraw() {
+    let mut deps = setup(default_init_msg());
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(500, "uusd")), deposit).unwrap();
+
+    deps.querier.with_balance(MOCK_CONTRACT_ADDR, coins(500, "uusd"));
+    execute(deps.as_mut(), env_at(START_TIME + PERIOD + 1), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD + 1), mock_info("owner", &[]), ExecuteMsg::CancelSale {});
+    assert_eq!(res, Err(ContractError::AlreadyWithdrawn {}));
+}
+
+#[test]
+fn test_withdraw_contribution() {
+    let mut init_msg = default_init_msg();
+    init_msg.rounds = vec![public_round(START_TIME, START_TIME + 500), public_round(START_TIME + 500, START_TIME + PERIOD)];
+    let mut deps = setup(init_msg);
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(300, "uusd")), deposit.clone()).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("user", &coins(200, "uusd")), deposit).unwrap();
+
+    let withdraw = ExecuteMsg::WithdrawContribution { amount: Uint128::from(250u128) };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("user", &[]), withdraw.clone());
+    assert_eq!(res, Err(ContractError::WithdrawalDisabled {}));
+
+    // penalty decays from 10% at the sale start to zero at the end
+    let msg = ExecuteMsg::SetWithdrawalPenalty {
+        withdrawal_penalty: Some(WithdrawalPenaltyInfo {
+            treasury: "treasury".to_string(),
+            start_rate: Decimal::percent(10),
+            end_rate: Decimal::zero(),
+        }),
+    };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("user", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("owner", &[]), msg).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("user", &[]), ExecuteMsg::WithdrawContribution { amount: Uint128::from(501u128) });
+    assert_eq!(res, Err(ContractError::ExceedContribution {}));
+
+    // the second round is emptied first, 5% penalty at mid sale
+    let res = execute(deps.as_mut(), env_at(START_TIME + 500), mock_info("user", &[]), withdraw).unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(238, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "treasury".to_string(),
+        amount: coins(12, "uusd"),
+    }));
+    assert_eq!(res.messages[2].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient { recp: "user".to_string(), amount: 250 }).unwrap(),
+        funds: vec![],
+    }));
+
+    let res: GetParticipantResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 500), QueryMsg::GetParticipant { user: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(res.data.fund_balance, Uint128::from(250u128));
+    assert_eq!(res.data.reward_balance, Uint128::from(250u128));
+
+    let info: PresaleInfoResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME + 500), QueryMsg::PresaleInfo {}).unwrap()).unwrap();
+    assert_eq!((info.rounds[0].raised, info.rounds[0].sold_amount), (Uint128::from(250u128), Uint128::from(250u128)));
+    assert_eq!((info.rounds[1].raised, info.rounds[1].sold_amount), (Uint128::zero(), Uint128::zero()));
+    assert_eq!(info.fund_assets[0].public_raised, Uint128::from(250u128));
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 500), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.total_raised, Uint128::from(250u128));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD), mock_info("user", &[]), ExecuteMsg::WithdrawContribution { amount: Uint128::from(10u128) });
+    assert_eq!(res, Err(ContractError::SaleEnded {}));
+}
+
+fn finalize_info() -> FinalizeInfo {
+    FinalizeInfo {
+        proceeds_receivers: vec![
+            ProceedsReceiverInfo { address: "treasury".to_string(), share: Decimal::percent(70) },
+            ProceedsReceiverInfo { address: "team".to_string(), share: Decimal::percent(30) },
+        ],
+        unsold_receiver: "owner".to_string(),
+        vesting_start_offset: 100,
+    }
+}
+
+#[test]
+fn test_finalize() {
+    let mut deps = setup(default_init_msg());
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(601, "uusd")), deposit.clone()).unwrap();
+
+    let env = env_at(START_TIME + PERIOD + 1);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Finalize {});
+    assert_eq!(res, Err(ContractError::FinalizeNotConfigured {}));
+
+    let mut finalize = finalize_info();
+    finalize.proceeds_receivers[1].share = Decimal::percent(40);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::SetFinalizeConfig { finalize: Some(finalize) });
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::SetFinalizeConfig { finalize: Some(finalize_info()) }).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("anyone", &[]), ExecuteMsg::Finalize {});
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+
+    deps.querier.with_balance(MOCK_CONTRACT_ADDR, coins(601, "uusd"));
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Finalize {}).unwrap();
+    assert_eq!(res.messages.len(), 5);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "treasury".to_string(),
+        amount: coins(420, "uusd"),
+    }));
+    // the last receiver takes the rounding remainder
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "team".to_string(),
+        amount: coins(181, "uusd"),
+    }));
+    assert_eq!(res.messages[2].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::SetStartTime { new_start_time: START_TIME + PERIOD + 100 }).unwrap(),
+        funds: vec![],
+    }));
+    // exactly the sold rewards are sent to vesting, the rest is returned
+    assert_eq!(res.messages[3].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "vesting".to_string(),
+            amount: Uint128::from(601u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.messages[4].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "owner".to_string(),
+            amount: Uint128::from(999399u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+    let event = &res.events[0];
+    assert_eq!(event.ty, "presale_finalized");
+    assert_eq!(event.attributes[0].value, "succeeded");
+    assert_eq!(event.attributes[4].value, "601uusd");
+    assert_eq!(event.attributes[8].value, (START_TIME + PERIOD + 100).to_string());
+
+    let status: GetSaleStatusResponse = from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::GetSaleStatus {}).unwrap()).unwrap();
+    assert!(status.finalized);
+    assert_eq!(status.escrowed_rewards, Uint128::zero());
+
+    // the presale is locked
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Finalize {});
+    assert_eq!(res, Err(ContractError::Finalized {}));
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::Finalized {}));
+    let res = execute(deps.as_mut(), env, mock_info("user", &coins(10, "uusd")), deposit);
+    assert_eq!(res, Err(ContractError::Finalized {}));
+}
+
+#[test]
+fn test_finalize_failed_sale() {
+    let mut init_msg = default_init_msg();
+    init_msg.finalize = Some(finalize_info());
+    let mut deps = setup(init_msg);
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), deposit).unwrap();
+
+    // all reward tokens are returned and vesting is not started
+    let env = env_at(START_TIME + PERIOD + 1);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Finalize {}).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "owner".to_string(),
+            amount: Uint128::from(1000000u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.events[0].attributes[0].value, "failed");
+
+    // participants are still refunded
+    execute(deps.as_mut(), env, mock_info("user", &[]), ExecuteMsg::ClaimRefund {}).unwrap();
+}
+
+#[test]
+fn test_deposit_partial_fill_at_hard_cap() {
+    let mut deps = setup(default_init_msg());
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(1200000, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(200000, "uusd"),
+    }));
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 1), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.public_sold_amount, Uint128::from(1000000u128));
+    assert_eq!(status.total_raised, Uint128::from(1000000u128));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("user2", &coins(100, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::HardCapReached {}));
+}
+
+#[test]
+fn test_private_deposit_partial_fill_at_allocation() {
+    let mut init_msg = default_init_msg();
+    init_msg.rounds = vec![
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + 100) },
+        public_round(START_TIME + 100, START_TIME + PERIOD),
+    ];
+    let mut deps = setup(init_msg);
+
+    let allo_info = AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::from(100u128) };
+    let msg = ExecuteMsg::DepositPrivateSale { allo_info, proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(150, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::ExceedAllocation {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::PrivateNotInProgress {}));
+}
+
+#[test]
+fn test_staking_tier_allocation() {
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_merkle_root = "00".repeat(32);
+    init_msg.rounds = vec![RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + PERIOD) }];
+    init_msg.staking = Some(StakingInfo {
+        contract: "staking".to_string(),
+        snapshot_time: START_TIME - 10,
+        base_allocation: Uint128::from(100u128),
+        tiers: vec![
+            Tier { min_bond: Uint128::from(100u128), multiplier: Decimal::one() },
+            Tier { min_bond: Uint128::from(1000u128), multiplier: Decimal::percent(300) },
+        ],
+    });
+    let mut deps = setup(init_msg);
+
+    deps.querier.with_bond_amount("user", Uint128::from(1000u128));
+    execute(deps.as_mut(), env_at(START_TIME - 20), mock_info("user", &[]), ExecuteMsg::RegisterStake {}).unwrap();
+
+    // stakes after the snapshot do not count
+    deps.querier.with_bond_amount("late", Uint128::from(1000u128));
+    let res = execute(deps.as_mut(), env_at(START_TIME - 10), mock_info("late", &[]), ExecuteMsg::RegisterStake {});
+    assert_eq!(res, Err(ContractError::SnapshotPassed {}));
+
+    let msg = ExecuteMsg::DepositPrivateSale { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("late", &coins(100, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    // unstaking after the snapshot lowers the tier
+    deps.querier.with_bond_amount("user", Uint128::from(500u128));
+    let tier: StakingTierResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::StakingTier { user: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(tier, StakingTierResponse {
+        snapshot_bond: Uint128::from(1000u128),
+        bond_amount: Uint128::from(500u128),
+        tier: Some(0),
+        allocation: Uint128::from(100u128),
+    });
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(150, "uusd")), msg).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+}
+
+#[test]
+fn test_staking_tier_reservation() {
+    let mut init_msg = default_init_msg();
+    init_msg.rounds = vec![
+        public_round(START_TIME, START_TIME + PERIOD),
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME + PERIOD, START_TIME + 2 * PERIOD) },
+    ];
+    init_msg.staking = Some(StakingInfo {
+        contract: "staking".to_string(),
+        snapshot_time: START_TIME - 10,
+        base_allocation: Uint128::from(100u128),
+        tiers: vec![Tier { min_bond: Uint128::from(1000u128), multiplier: Decimal::percent(300) }],
+    });
+    let mut deps = setup(init_msg);
+
+    deps.querier.with_bond_amount("staker", Uint128::from(1000u128));
+    execute(deps.as_mut(), env_at(START_TIME - 20), mock_info("staker", &[]), ExecuteMsg::RegisterStake {}).unwrap();
+
+    // the tier allocation is kept out of the public round
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("whale", &coins(1000000, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "whale".to_string(),
+        amount: coins(300, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("whale", &coins(100, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::SupplyReserved {}));
+
+    // the staker buys the reserved allocation in the private round
+    let msg = ExecuteMsg::DepositPrivateSale { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD), mock_info("staker", &coins(300, "uusd")), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + PERIOD), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.private_sold_amount + status.public_sold_amount, Uint128::from(1000000u128));
+}
+
+#[test]
+fn test_lock_weighted_allocation() {
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_merkle_root = "00".repeat(32);
+    init_msg.rounds = vec![RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + PERIOD) }];
+    init_msg.locking = Some(LockingInfo {
+        contract: "locking".to_string(),
+        allocation_rate: Decimal::one(),
+        min_lock_age: 100,
+        max_boost: Decimal::percent(200),
+        boost_period: 1000,
+    });
+    let mut deps = setup(init_msg);
+
+    deps.querier.with_lock_info("user", LockInfo { amount: Uint128::from(100u128), unlock_started: 0, locked_at: START_TIME - 500 });
+    deps.querier.with_lock_info("fresh", LockInfo { amount: Uint128::from(100u128), unlock_started: 0, locked_at: START_TIME - 50 });
+
+    // half of the boost period gives half of the max boost
+    let res: LockAllocationResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::LockAllocation { user: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(res, LockAllocationResponse {
+        locked_amount: Uint128::from(100u128),
+        lock_age: 500,
+        eligible: true,
+        allocation: Uint128::from(150u128),
+    });
+
+    let msg = ExecuteMsg::DepositPrivateSale { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("fresh", &coins(100, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(200, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+
+    // starting an unlock drops the boosted allocation
+    deps.querier.with_lock_info("user", LockInfo { amount: Uint128::from(1000u128), unlock_started: START_TIME + 1, locked_at: START_TIME - 500 });
+    let res = execute(deps.as_mut(), env_at(START_TIME + 2), mock_info("user", &coins(100, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+}
+
+#[test]
+fn test_drand_lottery() {
+    let mut init_msg = default_init_msg();
+    init_msg.lottery = Some(LotteryInfo {
+        ticket_source: TicketSource::Staking { contract: "staking".to_string() },
+        ticket_size: Uint128::from(100u128),
+        winners: 2,
+        allocation: Uint128::from(100u128),
+        registration_end: DRAND_GENESIS_TIME + (BEACON_ROUND - 2) * 30,
+        drand_public_key: DRAND_PUBLIC_KEY.to_string(),
+        drand_genesis_time: DRAND_GENESIS_TIME,
+        drand_period: 30,
+    });
+    let mut deps = setup(init_msg);
+
+    for (user, bond_amount) in [("alice", 1000u128), ("bob", 500), ("carol", 100), ("dave", 50)] {
+        deps.querier.with_bond_amount(user, Uint128::from(bond_amount));
+    }
+    for user in ["alice", "bob", "carol"] {
+        execute(deps.as_mut(), env_at(START_TIME - 200), mock_info(user, &[]), ExecuteMsg::RegisterLottery {}).unwrap();
+    }
+    let res = execute(deps.as_mut(), env_at(START_TIME - 200), mock_info("alice", &[]), ExecuteMsg::RegisterLottery {});
+    assert_eq!(res, Err(ContractError::AlreadyRegistered {}));
+
+    // alice unbonds and the same stake registers again from another wallet
+    deps.querier.with_bond_amount("alice", Uint128::zero());
+    deps.querier.with_bond_amount("eve", Uint128::from(1000u128));
+    execute(deps.as_mut(), env_at(START_TIME - 200), mock_info("eve", &[]), ExecuteMsg::RegisterLottery {}).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME - 200), mock_info("dave", &[]), ExecuteMsg::RegisterLottery {});
+    assert_eq!(res, Err(ContractError::NoTickets {}));
+    let registration_end = DRAND_GENESIS_TIME + (BEACON_ROUND - 2) * 30;
+    let res = execute(deps.as_mut(), env_at(registration_end), mock_info("dave", &[]), ExecuteMsg::RegisterLottery {});
+    assert_eq!(res, Err(ContractError::RegistrationClosed {}));
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(100, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::LotteryNotDrawn {}));
+
+    // only the first round after the registration closed is accepted
+    let msg = ExecuteMsg::SubmitBeacon {
+        round: BEACON_ROUND + 1,
+        previous_signature: BEACON_PREVIOUS_SIGNATURE.to_string(),
+        signature: BEACON_SIGNATURE.to_string(),
+    };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("anyone", &[]), msg);
+    assert_eq!(res, Err(ContractError::InvalidBeacon {}));
+
+    let msg = ExecuteMsg::SubmitBeacon {
+        round: BEACON_ROUND,
+        previous_signature: BEACON_PREVIOUS_SIGNATURE.to_string(),
+        signature: BEACON_SIGNATURE.to_string(),
+    };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("anyone", &[]), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("anyone", &[]), msg);
+    assert_eq!(res, Err(ContractError::AlreadyDrawn {}));
+
+    let info: PresaleInfoResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME), QueryMsg::PresaleInfo {}).unwrap()).unwrap();
+    let lottery = info.lottery.unwrap();
+    assert_eq!(lottery.total_tickets, Uint128::from(26u128));
+    assert_eq!(lottery.randomness, Some(BEACON_RANDOMNESS.to_string()));
+
+    let entry: LotteryEntryResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::LotteryEntry { user: "carol".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(entry, LotteryEntryResponse { tickets: Uint128::from(1u128), winner: false });
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("carol", &coins(100, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::NotWinner {}));
+
+    // both wallets won with the same stake, only the one still holding it deposits up to the lottery allocation
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(100, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::TicketsNotBacked {}));
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("eve", &coins(150, "uusd")), deposit).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "eve".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+}
+
+#[test]
+fn test_lottery_entrant_cap() {
+    let mut init_msg = default_init_msg();
+    init_msg.lottery = Some(LotteryInfo {
+        ticket_source: TicketSource::Staking { contract: "staking".to_string() },
+        ticket_size: Uint128::from(100u128),
+        winners: 2,
+        allocation: Uint128::from(100u128),
+        registration_end: DRAND_GENESIS_TIME,
+        drand_public_key: DRAND_PUBLIC_KEY.to_string(),
+        drand_genesis_time: DRAND_GENESIS_TIME,
+        drand_period: 30,
+    });
+    let mut deps = setup(init_msg);
+
+    for index in 0..=MAX_LOTTERY_ENTRANTS {
+        deps.querier.with_bond_amount(&format!("user{}", index), Uint128::from(100u128));
+    }
+    for index in 0..MAX_LOTTERY_ENTRANTS {
+        execute(deps.as_mut(), env_at(START_TIME), mock_info(&format!("user{}", index), &[]), ExecuteMsg::RegisterLottery {}).unwrap();
+    }
+    let user = format!("user{}", MAX_LOTTERY_ENTRANTS);
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info(&user, &[]), ExecuteMsg::RegisterLottery {});
+    assert_eq!(res, Err(ContractError::LotteryFull {}));
+}
+
+#[test]
+fn test_public_whitelist_modes() {
+    let allo_info = AlloInfo { public_allocation: Uint128::from(100u128), private_allocation: Uint128::from(50u128) };
+    let private_leaf = whitelist_leaf(MOCK_CONTRACT_ADDR, 1, "user", &allo_info);
+    let public_leaf = whitelist_leaf(MOCK_CONTRACT_ADDR, 2, "user", &allo_info);
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_merkle_root = hex::encode(whitelist_node(private_leaf, public_leaf));
+    init_msg.public_whitelist = PublicWhitelist::SharedRoot;
+    init_msg.rounds = vec![
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + 100) },
+        public_round(START_TIME + 100, START_TIME + PERIOD),
+    ];
+    let mut deps = setup(init_msg);
+
+    let msg = ExecuteMsg::Deposit { allo_info: allo_info.clone(), proof: vec![hex::encode(public_leaf)], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(50, "uusd")), msg).unwrap();
+
+    // public allocation comes on top of private purchases
+    let msg = ExecuteMsg::Deposit { allo_info: allo_info.clone(), proof: vec![hex::encode(private_leaf)], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(200, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(100, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::ExceedAllocation {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("other", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    // separate root replaces the shared one for public rounds
+    let public_allo = AlloInfo { public_allocation: Uint128::from(30u128), private_allocation: Uint128::zero() };
+    let msg = ExecuteMsg::SetPublicWhitelist {
+        public_whitelist: PublicWhitelist::SeparateRoot { merkle_root: hex::encode(whitelist_leaf(MOCK_CONTRACT_ADDR, 2, "other", &public_allo)) },
+    };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("owner", &[]), msg).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), ExecuteMsg::Deposit { allo_info, proof: vec![], permit: None });
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+    let msg = ExecuteMsg::Deposit { allo_info: public_allo, proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("other", &coins(50, "uusd")), msg).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "other".to_string(),
+        amount: coins(20, "uusd"),
+    }));
+}
+
+#[test]
+fn test_whitelist_leaf_transition() {
+    let allo_info = AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::from(100u128) };
+    let legacy_leaf = sha2::Sha256::digest(format!("user{}{}", allo_info.private_allocation, allo_info.public_allocation).as_bytes());
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_merkle_root = hex::encode(legacy_leaf);
+    init_msg.legacy_whitelist_until = START_TIME + 100;
+    init_msg.rounds = vec![
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + 100) },
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME + 100, START_TIME + PERIOD) },
+    ];
+    let mut deps = setup(init_msg);
+
+    // legacy leaves are accepted during the transition only
+    let msg = ExecuteMsg::Deposit { allo_info: allo_info.clone(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    // proofs of another round or another presale are rejected
+    for (contract, round_id) in [(MOCK_CONTRACT_ADDR, 1), ("other_presale", 2)] {
+        let merkle_root = hex::encode(whitelist_leaf(contract, round_id, "user", &allo_info));
+        execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("owner", &[]), ExecuteMsg::SetMerkleRoot { merkle_root }).unwrap();
+        let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg.clone());
+        assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+    }
+
+    let merkle_root = hex::encode(whitelist_leaf(MOCK_CONTRACT_ADDR, 2, "user", &allo_info));
+    execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("owner", &[]), ExecuteMsg::SetMerkleRoot { merkle_root }).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg).unwrap();
+}
+
+#[test]
+fn test_signature_whitelist() {
+    let secp_key = k256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+    let ed_key = ed25519_zebra::SigningKey::from([9u8; 32]);
+    let sign_secp = |allo_info: &AlloInfo, expiry: u64, nonce: u64| {
+        let hash = permit_hash(MOCK_CONTRACT_ADDR, 1, "user", allo_info, expiry, nonce);
+        let signature: k256::ecdsa::Signature = secp_key.sign_prehash(&hash).unwrap();
+        WhitelistPermit { expiry, nonce, signature: Binary::from(signature.to_bytes().to_vec()) }
+    };
+
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_merkle_root = hex::encode([0u8; 32]);
+    init_msg.whitelist_signer = Some(WhitelistSigner {
+        scheme: SignatureScheme::Secp256k1,
+        public_key: Binary::from(secp_key.verifying_key().to_encoded_point(true).as_bytes()),
+    });
+    init_msg.rounds = vec![RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + PERIOD) }];
+    let mut deps = setup(init_msg);
+
+    let allo_info = AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::from(100u128) };
+    let deposit = |permit: WhitelistPermit| ExecuteMsg::Deposit { allo_info: allo_info.clone(), proof: vec![], permit: Some(permit) };
+
+    // without proof nor permit the sender isn't whitelisted
+    let msg = ExecuteMsg::Deposit { allo_info: allo_info.clone(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    // signed allocation is honored
+    let permit = sign_secp(&allo_info, START_TIME + 10, 1);
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(150, "uusd")), deposit(permit.clone())).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), deposit(permit));
+    assert_eq!(res, Err(ContractError::PermitUsed {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 10), mock_info("user", &coins(10, "uusd")), deposit(sign_secp(&allo_info, START_TIME + 10, 2)));
+    assert_eq!(res, Err(ContractError::PermitExpired {}));
+
+    // permit of a smaller allocation can't be replayed with a larger one
+    let permit = sign_secp(&empty_allocation(), START_TIME + 10, 3);
+    let msg = ExecuteMsg::Deposit {
+        allo_info: AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::from(200u128) },
+        proof: vec![],
+        permit: Some(permit),
+    };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::VerificationFailed {}));
+
+    // only the owner rotates the signer
+    let msg = ExecuteMsg::SetWhitelistSigner { signer: Some(WhitelistSigner {
+        scheme: SignatureScheme::Ed25519,
+        public_key: Binary::from(<[u8; 32]>::from(ed25519_zebra::VerificationKey::from(&ed_key)).to_vec()),
+    }) };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), msg).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), deposit(sign_secp(&allo_info, START_TIME + 10, 4)));
+    assert_eq!(res, Err(ContractError::VerificationFailed {}));
+
+    let large_allocation = AlloInfo { public_allocation: Uint128::zero(), private_allocation: Uint128::from(200u128) };
+    let hash = permit_hash(MOCK_CONTRACT_ADDR, 1, "user", &large_allocation, START_TIME + 10, 4);
+    let permit = WhitelistPermit {
+        expiry: START_TIME + 10,
+        nonce: 4,
+        signature: Binary::from(<[u8; 64]>::from(ed_key.sign(&hash)).to_vec()),
+    };
+    let msg = ExecuteMsg::Deposit { allo_info: large_allocation, proof: vec![], permit: Some(permit) };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(100, "uusd")), msg).unwrap();
+}
+
+#[test]
+fn test_onchain_whitelist() {
+    let mut init_msg = default_init_msg();
+    init_msg.whitelist_mode = WhitelistMode::OnChain;
+    init_msg.public_whitelist = PublicWhitelist::SharedRoot;
+    init_msg.rounds = vec![
+        RoundMsg { kind: RoundKind::Private, ..public_round(START_TIME, START_TIME + 100) },
+        public_round(START_TIME + 100, START_TIME + PERIOD),
+    ];
+    let mut deps = setup(init_msg);
+
+    let entry = |address: &str, private: u128, public: u128| WhitelistEntry {
+        address: address.to_string(),
+        allo_info: AlloInfo { private_allocation: Uint128::from(private), public_allocation: Uint128::from(public) },
+    };
+    let msg = ExecuteMsg::UpdateWhitelist { add: vec![entry("alice", 100, 50), entry("bob", 100, 0), entry("carol", 10, 10)], remove: vec![] };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateWhitelist { add: vec![entry("bob", 40, 0)], remove: vec!["carol".to_string()] };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateWhitelist { add: (0..501).map(|i| entry(&format!("user{}", i), 1, 1)).collect(), remove: vec![] };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), msg);
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+
+    // claimed allocation is ignored, the on-chain entry applies
+    let large_allocation = AlloInfo { private_allocation: Uint128::from(1000u128), public_allocation: Uint128::zero() };
+    let msg = ExecuteMsg::Deposit { allo_info: large_allocation, proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &coins(100, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "bob".to_string(),
+        amount: coins(60, "uusd"),
+    }));
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(30, "uusd")), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("carol", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("carol", &coins(10, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::NotWhitelisted {}));
+
+    let res: WhitelistResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::Whitelist { start_after: None, limit: Some(1) }).unwrap(),
+    ).unwrap();
+    assert_eq!(res.entries.len(), 1);
+    assert_eq!(res.entries[0].address, "alice");
+    assert_eq!(res.entries[0].private_remaining, Uint128::from(70u128));
+    assert_eq!(res.entries[0].public_remaining, Uint128::from(50u128));
+
+    let res: WhitelistResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::Whitelist { start_after: Some("alice".to_string()), limit: None }).unwrap(),
+    ).unwrap();
+    assert_eq!(res.entries.len(), 1);
+    assert_eq!(res.entries[0].address, "bob");
+    assert_eq!(res.entries[0].private_remaining, Uint128::zero());
+}
+
+#[test]
+fn test_nft_gated_allocation() {
+    let mut init_msg = default_init_msg();
+    init_msg.nft_gate = Some(NftGateInfo { collection: "collection".to_string(), allocation_per_token: Uint128::from(50u128) });
+    let mut deps = setup(init_msg);
+    deps.querier.with_nft_owner("collection", "1", "alice");
+    deps.querier.with_nft_owner("collection", "2", "alice");
+    deps.querier.with_nft_owner("collection", "3", "bob");
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::NoTokenAllocation {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &[]), ExecuteMsg::ClaimTokenAllocation { token_ids: Some(vec!["3".to_string()]) });
+    assert_eq!(res, Err(ContractError::NotTokenOwner {}));
+
+    // all unused tokens are claimed when no ids are given
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &[]), ExecuteMsg::ClaimTokenAllocation { token_ids: None }).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(150, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "alice".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+
+    // a token passed to another wallet can't be claimed again
+    deps.querier.with_nft_owner("collection", "1", "bob");
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &[]), ExecuteMsg::ClaimTokenAllocation { token_ids: Some(vec!["1".to_string()]) });
+    assert_eq!(res, Err(ContractError::TokenUsed {}));
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &[]), ExecuteMsg::ClaimTokenAllocation { token_ids: None }).unwrap();
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &[]), ExecuteMsg::ClaimTokenAllocation { token_ids: None });
+    assert_eq!(res, Err(ContractError::NoTokenAllocation {}));
+
+    let res: TokenAllocationResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::TokenAllocation { user: "bob".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(res, TokenAllocationResponse { allocation: Uint128::from(50u128), remaining: Uint128::from(50u128) });
+}
+
+#[test]
+fn test_guardian_pause() {
+    let mut init_msg = default_init_msg();
+    init_msg.extend_on_pause = true;
+    init_msg.rounds = vec![public_round(START_TIME, START_TIME + 100), public_round(START_TIME + 100, START_TIME + PERIOD)];
+    let mut deps = setup(init_msg);
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 50), mock_info("owner", &[]), ExecuteMsg::Pause {});
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME + 50), mock_info("guardian", &[]), ExecuteMsg::Pause {}).unwrap();
+
+    let info: PresaleInfoResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME + 50), QueryMsg::PresaleInfo {}).unwrap()).unwrap();
+    assert!(info.paused);
+    assert_eq!(info.paused_at, Some(START_TIME + 50));
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 60), mock_info("user", &coins(10, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::Paused {}));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 60), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::Paused {}));
+
+    // rounds are moved by the 200 seconds paused
+    execute(deps.as_mut(), env_at(START_TIME + 250), mock_info("guardian", &[]), ExecuteMsg::Unpause {}).unwrap();
+    let info: PresaleInfoResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME + 250), QueryMsg::PresaleInfo {}).unwrap()).unwrap();
+    assert!(!info.paused);
+    assert_eq!((info.rounds[0].start_time, info.rounds[0].end_time), (START_TIME, START_TIME + 300));
+    assert_eq!((info.rounds[1].start_time, info.rounds[1].end_time), (START_TIME + 300, START_TIME + PERIOD + 200));
+
+    let res: RoundResponse = from_binary(&query(deps.as_ref(), env_at(START_TIME + 250), QueryMsg::CurrentRound {}).unwrap()).unwrap();
+    assert_eq!(res.round.unwrap().id, 1);
+    execute(deps.as_mut(), env_at(START_TIME + 250), mock_info("user", &coins(10, "uusd")), deposit).unwrap();
+}
+
+#[test]
+fn test_blocklist() {
+    let mut deps = setup(default_init_msg());
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), ExecuteMsg::SetCompliance { compliance: Some("compliance".to_string()) }).unwrap();
+
+    let msg = ExecuteMsg::Block { address: "user".to_string(), reason: 3 };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("compliance", &[]), msg).unwrap();
+    assert_eq!(res.events[0].ty, "blocklist");
+    assert_eq!(res.events[0].attributes[0].value, "block");
+    assert_eq!(res.events[0].attributes[3].value, "3");
+
+    let res: BlockedResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::Blocked { address: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(res.block.unwrap().blocked_at, START_TIME);
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), deposit.clone());
+    assert_eq!(res, Err(ContractError::Blocked {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), ExecuteMsg::Unblock { address: "user".to_string(), reason: 4 }).unwrap();
+    assert_eq!(res.events[0].attributes[0].value, "unblock");
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(10, "uusd")), deposit).unwrap();
+}
+
+#[test]
+fn test_recover_frozen() {
+    let mut deps = setup(default_init_msg());
+
+    let deposit = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(100, "uusd")), deposit).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), ExecuteMsg::Block { address: "user".to_string(), reason: 3 }).unwrap();
+
+    let msg = ExecuteMsg::RecoverFrozen { address: "user".to_string(), recovery: "recovery".to_string() };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::SaleNotFailed {}));
+
+    // the sale missed the soft cap, the refund of the blocked user is frozen
+    let env = env_at(START_TIME + PERIOD);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), ExecuteMsg::ClaimRefund {});
+    assert_eq!(res, Err(ContractError::Blocked {}));
+    let res = execute(deps.as_mut(), env.clone(), mock_info("compliance", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::RecoverFrozen { address: "other".to_string(), recovery: "recovery".to_string() });
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "recovery".to_string(),
+        amount: coins(100, "uusd"),
+    }));
+    assert_eq!(res.events[0].ty, "recover_frozen");
+    assert_eq!(res.events[0].attributes[1].value, "user");
+    assert_eq!(res.events[0].attributes[3].value, "100");
+
+    let res = execute(deps.as_mut(), env, mock_info("owner", &[]), msg);
+    assert_eq!(res, Err(ContractError::NothingToRefund {}));
+}
+
+#[test]
+fn test_attestation_gating() {
+    let mut init_msg = default_init_msg();
+    init_msg.attestation = Some(AttestationInfo {
+        registry: "registry".to_string(),
+        blocked_jurisdictions: vec!["us".to_string()],
+    });
+    let mut deps = setup(init_msg);
+    deps.querier.with_attestation("alice", "CH", true);
+    deps.querier.with_attestation("bob", "US", true);
+    deps.querier.with_attestation("carol", "CH", false);
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    for (user, error) in [("bob", ContractError::BlockedJurisdiction {}), ("carol", ContractError::NotAttested {}), ("dave", ContractError::NotAttested {})] {
+        let res = execute(deps.as_mut(), env_at(START_TIME), mock_info(user, &coins(10, "uusd")), msg.clone());
+        assert_eq!(res, Err(error));
+    }
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("alice", &coins(10, "uusd")), msg.clone()).unwrap();
+
+    // owner lifts the jurisdiction block
+    let attestation = AttestationInfo { registry: "registry".to_string(), blocked_jurisdictions: vec![] };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &[]), ExecuteMsg::SetAttestation { attestation: Some(attestation.clone()) });
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("owner", &[]), ExecuteMsg::SetAttestation { attestation: Some(attestation) }).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("bob", &coins(10, "uusd")), msg).unwrap();
+}
+
+#[test]
+fn test_cw20_fund_token() {
+    let mut init_msg = default_init_msg();
+    init_msg.fund_assets[0].info = AssetInfo::Token { contract_addr: "fund_token".to_string() };
+    init_msg.fund_assets[0].decimals = Some(8);
+    init_msg.soft_cap = Uint128::zero();
+    let mut deps = setup(init_msg);
+
+    // native coins are rejected
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::Funds {}));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "user".to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_binary(&Cw20HookMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None }).unwrap(),
+    });
+
+    // only accepted fund token can call the hook
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("other_token", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::Funds {}));
+
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("fund_token", &[]), msg).unwrap();
+
+    // 1000 units of 8 decimals fund token buys 10 units of 6 decimals reward token
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 1), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.public_sold_amount, Uint128::from(10u128));
+
+    deps.querier.with_token_balance("fund_token", MOCK_CONTRACT_ADDR, Uint128::from(1000u128));
+    let res = execute(
+        deps.as_mut(),
+        env_at(START_TIME + PERIOD + 1),
+        mock_info("owner", &[]),
+        ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() },
+    ).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "fund_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: "owner".to_string(), amount: Uint128::from(1000u128) }).unwrap(),
+        funds: vec![],
+    }));
+}
+
+#[test]
+fn test_multiple_fund_assets() {
+    let mut init_msg = default_init_msg();
+    // 1 luna buys 2 reward tokens, so it's worth 2 uusd
+    init_msg.fund_assets.push(FundAssetMsg {
+        info: AssetInfo::NativeToken { denom: "uluna".to_string() },
+        exchange_rate: Uint128::from(50000000u128),
+        decimals: None,
+        cap: Uint128::from(300u128),
+    });
+    let mut deps = setup(init_msg);
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(100, "uusd")), msg.clone()).unwrap();
+
+    // luna deposit is capped by its own cap
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(400, "uluna")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(100, "uluna"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(1, "uluna")), msg.clone());
+    assert_eq!(res, Err(ContractError::AssetCapReached {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(1, "ukrw")), msg);
+    assert_eq!(res, Err(ContractError::Funds {}));
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 1), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.public_sold_amount, Uint128::from(700u128));
+    assert_eq!(status.total_raised, Uint128::from(700u128));
+    assert_eq!(status.fund_assets[0].public_raised, Uint128::from(100u128));
+    assert_eq!(status.fund_assets[1].public_raised, Uint128::from(300u128));
+
+    // the soft cap is reached, no refunds, proceeds are withdrawn in each deposited asset
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD + 1), mock_info("user", &[]), ExecuteMsg::ClaimRefund {});
+    assert_eq!(res, Err(ContractError::SaleNotFailed {}));
+
+    deps.querier.with_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(100, "uusd"), Coin::new(300, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        env_at(START_TIME + PERIOD + 1),
+        mock_info("owner", &[]),
+        ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() },
+    ).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(300, "uluna"),
+    }));
+}
+
+#[test]
+fn test_oracle_usd_pricing() {
+    let mut init_msg = default_init_msg();
+    init_msg.fund_assets[0].info = AssetInfo::NativeToken { denom: "uluna".to_string() };
+    init_msg.fund_assets[0].exchange_rate = Uint128::zero();
+    init_msg.total_rewards_amount = Uint128::from(100000000u128);
+    // 0.5 USD per reward token, prices valid for a minute
+    init_msg.oracle = Some(OracleInfo {
+        contract: "oracle".to_string(),
+        token_price: Uint128::from(50000000u128),
+        max_price_age: 60,
+    });
+    let mut deps = setup(init_msg);
+    deps.querier.with_oracle_price("uluna", Decimal::from_ratio(5u128, 2u128), START_TIME);
+
+    // 1 luna at 2.5 USD buys 5 reward tokens
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME + 60), mock_info("user", &coins(1000000, "uluna")), msg.clone()).unwrap();
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 60), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.public_sold_amount, Uint128::from(5000000u128));
+    assert_eq!(status.total_raised, Uint128::from(2500000u128));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 61), mock_info("user", &coins(1000000, "uluna")), msg.clone());
+    assert_eq!(res, Err(ContractError::StalePrice {}));
+
+    // prices never expire with the max age
+    let mut init_msg = default_init_msg();
+    init_msg.fund_assets[0].info = AssetInfo::NativeToken { denom: "uluna".to_string() };
+    init_msg.oracle = Some(OracleInfo {
+        contract: "oracle".to_string(),
+        token_price: Uint128::from(50000000u128),
+        max_price_age: u64::MAX,
+    });
+    let mut deps = setup(init_msg);
+    deps.querier.with_oracle_price("uluna", Decimal::from_ratio(5u128, 2u128), START_TIME);
+    execute(deps.as_mut(), env_at(START_TIME + 61), mock_info("user", &coins(100, "uluna")), msg).unwrap();
+}
+
+#[test]
+fn test_sale_rounds() {
+    let mut init_msg = default_init_msg();
+    init_msg.rounds = vec![
+        RoundMsg { wallet_cap: Uint128::from(100u128), ..public_round(START_TIME, START_TIME + 100) },
+        // second round sells reward tokens at twice the price
+        RoundMsg {
+            exchange_rate: Some(Uint128::from(200000000u128)),
+            cap: Uint128::from(150u128),
+            ..public_round(START_TIME + 200, START_TIME + PERIOD)
+        },
+    ];
+    let mut deps = setup(init_msg);
+
+    let rounds: RoundsResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME - 1), QueryMsg::UpcomingRounds {}).unwrap(),
+    ).unwrap();
+    assert_eq!(rounds.rounds.len(), 2);
+
+    // wallet cap of the first round
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(150, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(50, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::ExceedAllocation {}));
+
+    // no round between the first and the second one
+    let res = execute(deps.as_mut(), env_at(START_TIME + 100), mock_info("user", &coins(10, "uusd")), msg.clone());
+    assert_eq!(res, Err(ContractError::RoundNotInProgress {}));
+    let round: RoundResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 100), QueryMsg::CurrentRound {}).unwrap(),
+    ).unwrap();
+    assert_eq!(round.round, None);
+
+    // round cap of the second round
+    let res = execute(deps.as_mut(), env_at(START_TIME + 200), mock_info("user", &coins(400, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(100, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + 200), mock_info("user2", &coins(10, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::RoundCapReached {}));
+
+    let round: RoundResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + 200), QueryMsg::CurrentRound {}).unwrap(),
+    ).unwrap();
+    let round = round.round.unwrap();
+    assert_eq!(round.id, 2);
+    assert_eq!(round.sold_amount, Uint128::from(150u128));
+    assert_eq!(round.raised, Uint128::from(300u128));
+
+    // ended rounds can't be updated
+    let res = execute(
+        deps.as_mut(),
+        env_at(START_TIME + 200),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateRound { round_id: 1, round: public_round(START_TIME + 300, START_TIME + 400) },
+    );
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+    execute(
+        deps.as_mut(),
+        env_at(START_TIME + 200),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateRound { round_id: 2, round: public_round(START_TIME + 200, START_TIME + 2 * PERIOD) },
+    ).unwrap();
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + PERIOD + 1), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.outcome, SaleOutcome::Pending);
+}
+
+fn simulate_deposit(deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, seconds: u64, amount: u128) -> SimulateDepositResponse {
+    let msg = QueryMsg::SimulateDeposit {
+        asset: AssetInfo::NativeToken { denom: "uusd".to_string() },
+        amount: Uint128::from(amount),
+    };
+    from_binary(&query(deps.as_ref(), env_at(seconds), msg).unwrap()).unwrap()
+}
+
+#[test]
+fn test_linear_curve_is_path_independent() {
+    let mut init_msg = default_init_msg();
+    // price doubles once a whole reward token is sold
+    init_msg.rounds[0].curve = Some(PricingCurve::Linear { slope: Uint128::from(100000000u128) });
+
+    let mut deps = setup(init_msg.clone());
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let mut paid = Uint128::zero();
+    for _ in 0..3 {
+        let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(100000, "uusd")), msg.clone()).unwrap();
+        paid += res.attributes.iter().find(|attr| attr.key == "amount").unwrap().value.parse::<Uint128>().unwrap();
+    }
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert!(status.public_sold_amount < Uint128::from(300000u128));
+
+    // later buyers pay more per token
+    let next = simulate_deposit(&deps, START_TIME, 100000);
+    assert!(next.reward_amount < simulate_deposit(&setup(init_msg.clone()), START_TIME, 100000).reward_amount);
+
+    // one deposit of the same total buys the same amount
+    let deps = setup(init_msg);
+    let simulation = simulate_deposit(&deps, START_TIME, paid.u128());
+    assert_eq!(simulation.reward_amount, status.public_sold_amount);
+    assert_eq!(simulation.accepted_amount, paid);
+    assert_eq!(simulation.refund_amount, Uint128::zero());
+}
+
+#[test]
+fn test_exponential_curve() {
+    let mut init_msg = default_init_msg();
+    // price doubles every 0.1 reward token
+    init_msg.rounds[0].curve = Some(PricingCurve::Exponential {
+        growth: Decimal::percent(200),
+        step: Uint128::from(100000u128),
+    });
+    let deps = setup(init_msg);
+
+    let simulation = simulate_deposit(&deps, START_TIME, 300000);
+    assert_eq!(simulation.reward_amount, Uint128::from(200000u128));
+    assert_eq!(simulation.accepted_amount, Uint128::from(300000u128));
+
+    let simulation = simulate_deposit(&deps, START_TIME, 350000);
+    assert_eq!(simulation.reward_amount, Uint128::from(212500u128));
+}
+
+#[test]
+fn test_dutch_auction() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::DutchAuction {
+        start_price: Uint128::from(200000000u128),
+        floor_price: Uint128::from(100000000u128),
+    };
+    let mut deps = setup(init_msg);
+
+    // bids are escrowed, vesting is updated on claim
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(1000000, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user", &[]), ExecuteMsg::Claim {});
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+
+    // supply sells out at 1.5
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("user2", &coins(900000, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user2".to_string(),
+        amount: coins(150000, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("user3", &coins(100, "uusd")), msg);
+    assert_eq!(res, Err(ContractError::HardCapReached {}));
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + PERIOD / 2), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.clearing_price, Some(Uint128::from(150000000u128)));
+
+    // first bidder pays the clearing price and gets the difference back
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("user", &[]), ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user".to_string(),
+        amount: coins(250000, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient { recp: "user".to_string(), amount: 500000 }).unwrap(),
+        funds: vec![],
+    }));
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("user", &[]), ExecuteMsg::Claim {});
+    assert_eq!(res, Err(ContractError::AlreadyClaimed {}));
+
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("user2", &[]), ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // claimed allocations are not registered again once the sale ended
+    let msg = ExecuteMsg::RegisterAllocations { limit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME + PERIOD / 2), mock_info("anyone", &[]), msg.clone());
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+    let env = env_at(START_TIME + PERIOD + 1);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // only the proceeds are withdrawn
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(1500000, "uusd"),
+    }));
+    let res = execute(deps.as_mut(), env, mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::AlreadyWithdrawn {}));
+}
+
+#[test]
+fn test_dutch_auction_soft_cap() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::DutchAuction {
+        start_price: Uint128::from(200000000u128),
+        floor_price: Uint128::from(100000000u128),
+    };
+    init_msg.soft_cap = Uint128::from(600u128);
+    let mut deps = setup(init_msg);
+
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(1000, "uusd")), msg).unwrap();
+
+    // 1000 escrowed at 2.0, but 500 kept at the floor price
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(START_TIME + PERIOD), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.total_raised, Uint128::from(1000u128));
+    assert_eq!(status.outcome, SaleOutcome::Failed);
+}
+
+#[test]
+fn test_sealed_bid_auction() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::SealedBid { reserve_price: Uint128::from(100000000u128), reveal_period: 100 };
+    let mut deps = setup(init_msg);
+
+    let res = execute(
+        deps.as_mut(),
+        env_at(START_TIME),
+        mock_info("user", &coins(100, "uusd")),
+        ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None },
+    );
+    assert_eq!(res, Err(ContractError::UnsupportedSaleMode {}));
+
+    let bids = [
+        ("user1", 1000000u128, 200000000u128, 400000u128),
+        ("user2", 1000000u128, 120000000u128, 800000u128),
+        ("user3", 500000u128, 300000000u128, 100000u128),
+    ];
+    for (user, escrow, price, amount) in bids {
+        let commitment = bid_commitment(MOCK_CONTRACT_ADDR, user, Uint128::from(price), Uint128::from(amount), user);
+        execute(deps.as_mut(), env_at(START_TIME), mock_info(user, &coins(escrow, "uusd")), ExecuteMsg::CommitBid { commitment }).unwrap();
+    }
+
+    // a copied commitment can't be revealed by another bidder
+    let commitment = bid_commitment(MOCK_CONTRACT_ADDR, "user1", Uint128::from(200000000u128), Uint128::from(400000u128), "user1");
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("copier", &coins(1000000, "uusd")), ExecuteMsg::CommitBid { commitment }).unwrap();
+
+    let reveal = |price: u128, amount: u128, salt: &str| ExecuteMsg::RevealBid {
+        price: Uint128::from(price),
+        amount: Uint128::from(amount),
+        salt: salt.to_string(),
+    };
+    let res = execute(deps.as_mut(), env_at(START_TIME + 1), mock_info("user1", &[]), reveal(200000000, 400000, "user1"));
+    assert_eq!(res, Err(ContractError::RevealNotInProgress {}));
+
+    let end = START_TIME + PERIOD;
+    let res = execute(deps.as_mut(), env_at(end), mock_info("user1", &[]), reveal(200000000, 400000, "other"));
+    assert_eq!(res, Err(ContractError::InvalidCommitment {}));
+    execute(deps.as_mut(), env_at(end), mock_info("user1", &[]), reveal(200000000, 400000, "user1")).unwrap();
+    let res = execute(deps.as_mut(), env_at(end), mock_info("copier", &[]), reveal(200000000, 400000, "user1"));
+    assert_eq!(res, Err(ContractError::InvalidCommitment {}));
+    execute(deps.as_mut(), env_at(end), mock_info("user2", &[]), reveal(120000000, 800000, "user2")).unwrap();
+    // user3 never reveals
+
+    let res = execute(deps.as_mut(), env_at(end + 99), mock_info("anyone", &[]), ExecuteMsg::Settle {});
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::Settle {}).unwrap();
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(end + 100), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.clearing_price, Some(Uint128::from(120000000u128)));
+    assert_eq!(status.fill_ratio, Some(Decimal::percent(75)));
+    assert_eq!(status.public_sold_amount, Uint128::from(1000000u128));
+
+    // winners are registered in vesting, the marginal bid is partially filled, addresses blocked in vesting are skipped
+    deps.querier.with_vesting_block("user2", 1);
+    let res = execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::RegisterAllocations { limit: None }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "vesting".to_string(),
+        msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient { recp: "user1".to_string(), amount: 400000 }).unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.attributes[3].value, "user2");
+
+    // winners pay the clearing price and get the rest of the escrow back, vesting is updated unless registered
+    let expected = [("user1", 520000u128, None), ("user2", 280000u128, Some(600000u64)), ("user3", 500000u128, None)];
+    for (user, refund, reward) in expected {
+        let res = execute(deps.as_mut(), env_at(end + 100), mock_info(user, &[]), ExecuteMsg::Claim {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: user.to_string(),
+            amount: coins(refund, "uusd"),
+        }));
+        match reward {
+            Some(amount) => assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "vesting".to_string(),
+                msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient { recp: user.to_string(), amount }).unwrap(),
+                funds: vec![],
+            })),
+            None => assert_eq!(res.messages.len(), 1),
+        }
+    }
+}
+
+#[test]
+fn test_sealed_bid_multiple_fund_assets() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::SealedBid { reserve_price: Uint128::from(100000000u128), reveal_period: 100 };
+    init_msg.total_rewards_amount = Uint128::from(1000u128);
+    // 1 luna is worth 2 uusd
+    init_msg.fund_assets.push(FundAssetMsg {
+        info: AssetInfo::NativeToken { denom: "uluna".to_string() },
+        exchange_rate: Uint128::from(50000000u128),
+        decimals: None,
+        cap: Uint128::from(10000u128),
+    });
+    let mut deps = setup(init_msg);
+
+    let bids = [
+        ("user1", 1200u128, "uusd", 200000000u128, 600u128),
+        ("user2", 1000u128, "uluna", 120000000u128, 500u128),
+        ("user3", 600u128, "uluna", 150000000u128, 800u128),
+    ];
+    for &(user, escrow, denom, price, amount) in bids.iter() {
+        let commitment = bid_commitment(MOCK_CONTRACT_ADDR, user, Uint128::from(price), Uint128::from(amount), user);
+        execute(deps.as_mut(), env_at(START_TIME), mock_info(user, &coins(escrow, denom)), ExecuteMsg::CommitBid { commitment }).unwrap();
+    }
+    let end = START_TIME + PERIOD;
+    for &(user, _, _, price, amount) in bids.iter() {
+        let msg = ExecuteMsg::RevealBid { price: Uint128::from(price), amount: Uint128::from(amount), salt: user.to_string() };
+        execute(deps.as_mut(), env_at(end), mock_info(user, &[]), msg).unwrap();
+    }
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::Settle {}).unwrap();
+
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(end + 100), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.clearing_price, Some(Uint128::from(150000000u128)));
+    assert_eq!(status.fill_ratio, Some(Decimal::percent(50)));
+
+    // every escrowed deposit is paid out, either as a refund or as proceeds in the asset it was paid in
+    let mut paid = [0u128; 2];
+    let mut count = |res: &Response| for msg in res.messages.iter() {
+        if let CosmosMsg::Bank(BankMsg::Send { amount, .. }) = &msg.msg {
+            for coin in amount.iter() {
+                paid[if coin.denom == "uusd" { 0 } else { 1 }] += coin.amount.u128();
+            }
+        }
+    };
+
+    let res = execute(deps.as_mut(), env_at(end + 100), mock_info("user1", &[]), ExecuteMsg::Claim {}).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "user1".to_string(),
+        amount: coins(300, "uusd"),
+    }));
+    count(&res);
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::RegisterAllocations { limit: None }).unwrap();
+
+    let res = execute(deps.as_mut(), env_at(end + 100), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(900, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(300, "uluna"),
+    }));
+    count(&res);
+
+    for &(user, refund) in [("user2", 1000u128), ("user3", 300u128)].iter() {
+        let res = execute(deps.as_mut(), env_at(end + 100), mock_info(user, &[]), ExecuteMsg::Claim {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: user.to_string(),
+            amount: coins(refund, "uluna"),
+        }));
+        count(&res);
+    }
+    assert_eq!(paid, [1200, 1600]);
+}
+
+#[test]
+fn test_sealed_bid_rounding() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::SealedBid { reserve_price: Uint128::from(100000000u128), reveal_period: 100 };
+    init_msg.total_rewards_amount = Uint128::from(3u128);
+    init_msg.soft_cap = Uint128::from(1u128);
+    let mut deps = setup(init_msg);
+
+    // every winner pays 1.5 uusd, rounded down to 1
+    let end = START_TIME + PERIOD;
+    let users = ["user1", "user2", "user3"];
+    for user in users.iter() {
+        let commitment = bid_commitment(MOCK_CONTRACT_ADDR, user, Uint128::from(150000000u128), Uint128::from(1u128), user);
+        execute(deps.as_mut(), env_at(START_TIME), mock_info(user, &coins(2, "uusd")), ExecuteMsg::CommitBid { commitment }).unwrap();
+    }
+    for user in users.iter() {
+        let msg = ExecuteMsg::RevealBid { price: Uint128::from(150000000u128), amount: Uint128::from(1u128), salt: user.to_string() };
+        execute(deps.as_mut(), env_at(end), mock_info(user, &[]), msg).unwrap();
+    }
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::Settle {}).unwrap();
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::RegisterAllocations { limit: None }).unwrap();
+
+    // proceeds are what is left of the escrow once refunds are paid, not the rounded total value
+    let res = execute(deps.as_mut(), env_at(end + 100), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(3, "uusd"),
+    }));
+    for user in users.iter() {
+        let res = execute(deps.as_mut(), env_at(end + 100), mock_info(user, &[]), ExecuteMsg::Claim {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: user.to_string(),
+            amount: coins(1, "uusd"),
+        }));
+    }
+}
+
+#[test]
+fn test_sealed_bid_soft_cap() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::SealedBid { reserve_price: Uint128::from(100000000u128), reveal_period: 100 };
+    let mut deps = setup(init_msg);
+
+    let commitment = bid_commitment(MOCK_CONTRACT_ADDR, "user", Uint128::from(100000000u128), Uint128::from(1000u128), "salt");
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user", &coins(1000, "uusd")), ExecuteMsg::CommitBid { commitment }).unwrap();
+
+    // escrow above the soft cap, but no bid is revealed
+    let end = START_TIME + PERIOD;
+    execute(deps.as_mut(), env_at(end + 100), mock_info("anyone", &[]), ExecuteMsg::Settle {}).unwrap();
+    let status: GetSaleStatusResponse = from_binary(
+        &query(deps.as_ref(), env_at(end + 100), QueryMsg::GetSaleStatus {}).unwrap(),
+    ).unwrap();
+    assert_eq!(status.total_raised, Uint128::from(1000u128));
+    assert_eq!(status.outcome, SaleOutcome::Failed);
+}
+
+#[test]
+fn test_fair_launch_pro_rata() {
+    let mut init_msg = default_init_msg();
+    init_msg.sale_mode = SaleMode::FairLaunch { target_raise: Uint128::from(1000000u128) };
+    let mut deps = setup(init_msg);
+
+    // any amount is escrowed, vesting is updated on claim
+    let msg = ExecuteMsg::Deposit { allo_info: empty_allocation(), proof: vec![], permit: None };
+    let res = execute(deps.as_mut(), env_at(START_TIME), mock_info("user1", &coins(1000000, "uusd")), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 0);
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user2", &coins(500000, "uusd")), msg.clone()).unwrap();
+    execute(deps.as_mut(), env_at(START_TIME), mock_info("user3", &coins(500000, "uusd")), msg).unwrap();
+
+    let end = START_TIME + PERIOD;
+    let res = execute(deps.as_mut(), env_at(end - 1), mock_info("anyone", &[]), ExecuteMsg::Settle {});
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+    let res = execute(deps.as_mut(), env_at(end), mock_info("user1", &[]), ExecuteMsg::Claim {});
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+    // rewards owed to buyers are unknown before settlement
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::WithdrawUnsoldToken { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::StillInProgress {}));
+    execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::Settle {}).unwrap();
+    let res = execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::Settle {});
+    assert_eq!(res, Err(ContractError::AlreadySettled {}));
+
+    // twice oversubscribed, half of every contribution is filled
+    for (user, amount) in [("user1", 500000u128), ("user2", 250000u128)] {
+        let res = execute(deps.as_mut(), env_at(end), mock_info(user, &[]), ExecuteMsg::Claim {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: user.to_string(),
+            amount: coins(amount, "uusd"),
+        }));
+        assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "vesting".to_string(),
+            msg: to_binary(&vesting::msg::ExecuteMsg::UpdateRecipient { recp: user.to_string(), amount: amount as u64 }).unwrap(),
+            funds: vec![],
+        }));
+    }
+
+    // proceeds are known once every participant is registered
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() });
+    assert_eq!(res, Err(ContractError::AllocationsNotRegistered {}));
+
+    // the whole supply is sold, nothing is left to withdraw
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::WithdrawUnsoldToken { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // vesting starts only once every allocation is registered
+    execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::SetFinalizeConfig { finalize: Some(finalize_info()) }).unwrap();
+    let res = execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::Finalize {});
+    assert_eq!(res, Err(ContractError::AllocationsNotRegistered {}));
+
+    let res = execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::RegisterAllocations { limit: Some(1) }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(res.attributes[2].value, "2");
+    // user3 is blocked in vesting, the rewards are kept here
+    deps.querier.with_vesting_block("user3", 1);
+    let res = execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::RegisterAllocations { limit: None }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(res.attributes[3].value, "user3");
+
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::WithdrawFunds { receiver: "owner".to_string() }).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "owner".to_string(),
+        amount: coins(1000000, "uusd"),
+    }));
+
+    let res = execute(deps.as_mut(), env_at(end), mock_info("anyone", &[]), ExecuteMsg::Finalize {}).unwrap();
+    assert_eq!(res.events[0].attributes[8].value, (end + 100).to_string());
+    assert_eq!(res.events[0].attributes[9].value, "250000");
+
+    // vesting only receives the registered allocations, the supply is sold out
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "vesting".to_string(),
+            amount: Uint128::from(750000u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+
+    // the unspent escrow and the frozen rewards are recovered once
+    execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), ExecuteMsg::Block { address: "user3".to_string(), reason: 3 }).unwrap();
+    let res = execute(deps.as_mut(), env_at(end), mock_info("user3", &[]), ExecuteMsg::Claim {});
+    assert_eq!(res, Err(ContractError::Blocked {}));
+    let msg = ExecuteMsg::RecoverFrozen { address: "user3".to_string(), recovery: "recovery".to_string() };
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+        to_address: "recovery".to_string(),
+        amount: coins(250000, "uusd"),
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "recovery".to_string(),
+            amount: Uint128::from(250000u128),
+        }).unwrap(),
+        funds: vec![],
+    }));
+    assert_eq!(res.events[0].attributes[3].value, "250000");
+    assert_eq!(res.events[0].attributes[4].value, "250000");
+    let res = execute(deps.as_mut(), env_at(end), mock_info("owner", &[]), msg);
+    assert_eq!(res, Err(ContractError::NothingToClaim {}));
+}
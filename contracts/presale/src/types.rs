@@ -1,3 +1,5 @@
+use std::fmt;
+
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -20,3 +22,25 @@ impl From<OrderBy> for Order {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
+}
+
+impl AssetInfo {
+    pub fn is_native_token(&self) -> bool {
+        matches!(self, AssetInfo::NativeToken { .. })
+    }
+}
+
+impl fmt::Display for AssetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetInfo::NativeToken { denom } => write!(f, "{}", denom),
+            AssetInfo::Token { contract_addr } => write!(f, "{}", contract_addr),
+        }
+    }
+}
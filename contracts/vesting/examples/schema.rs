@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use vesting::msg::{UsersCountResponse, GetUsersResponse, GetUserResponse, ExecuteMsg, InstantiateMsg, QueryMsg, AmountResponse};
+use vesting::msg::{UsersCountResponse, GetUsersResponse, GetUserResponse, ExecuteMsg, InstantiateMsg, QueryMsg, AmountResponse, BlockedResponse};
 use vesting::state::State;
 
 fn main() {
@@ -20,4 +20,5 @@ fn main() {
     export_schema(&schema_for!(GetUsersResponse), &out_dir);
     export_schema(&schema_for!(GetUserResponse), &out_dir);
     export_schema(&schema_for!(AmountResponse), &out_dir);
+    export_schema(&schema_for!(BlockedResponse), &out_dir);
 }
@@ -1,10 +1,10 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, StdError, CosmosMsg, WasmMsg, Uint128};
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult, StdError, CosmosMsg, WasmMsg, Uint128};
 use cw20::Cw20ExecuteMsg;
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, UsersCountResponse, GetUserResponse, GetUsersResponse, AmountResponse, MigrateMsg};
-use crate::state::{RECIPIENTS, UserInfo, State, STATE, ACCURACY};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, UsersCountResponse, GetUserResponse, GetUsersResponse, AmountResponse, BlockedResponse, MigrateMsg};
+use crate::state::{RECIPIENTS, BLOCKLIST, BlockInfo, UserInfo, State, STATE, ACCURACY};
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -25,7 +25,8 @@ pub fn instantiate(
         userlist: vec![],
         start_time: 0,
         total_vesting_amount: 0,
-        distribution_amount: msg.distribution_amount
+        distribution_amount: msg.distribution_amount,
+        compliance: None,
     };
 
     STATE.save(deps.storage, &state)?;
@@ -54,7 +55,12 @@ pub fn execute(
         ExecuteMsg::SetWorker { worker } => execute_set_worker(deps, info, worker),
         ExecuteMsg::SetStartTime { new_start_time } => execute_set_start_time(deps, env, info, new_start_time),
         ExecuteMsg::UpdateRecipient { recp, amount } => execute_update_recipient(deps, env, info, recp, amount),
-        ExecuteMsg::Withdraw {} => execute_withdraw(deps, env, info)
+        ExecuteMsg::ResetRecipient { recp } => execute_reset_recipient(deps, info, recp),
+        ExecuteMsg::Withdraw {} => execute_withdraw(deps, env, info),
+        ExecuteMsg::SetCompliance { compliance } => execute_set_compliance(deps, info, compliance),
+        ExecuteMsg::Block { address, reason } => execute_block(deps, env, info, address, reason),
+        ExecuteMsg::Unblock { address, reason } => execute_unblock(deps, info, address, reason),
+        ExecuteMsg::RecoverFrozen { address, recovery } => execute_recover_frozen(deps, info, address, recovery),
     }
 }
 
@@ -85,29 +91,18 @@ pub fn execute_set_worker(deps: DepsMut, info: MessageInfo, worker: String) -> S
         return Err(StdError::generic_err("unauthorized"));
     }
 
-    state.worker = worker.clone();
+    state.worker = worker;
     STATE.save(deps.storage, &state)?;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: deps.api.addr_humanize(&state.reward_token)?.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
-            spender: deps.api.addr_humanize(&worker)?.to_string(),
-            amount: Uint128::MAX,
-            expires: None
-        })?,
-        funds: vec![],
-    }));
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("method", "set_worker"))
+    Ok(Response::new().add_attribute("method", "set_worker"))
 }
 
 pub fn execute_set_start_time(deps: DepsMut, env: Env, info: MessageInfo, new_start_time: u64) -> StdResult<Response> {
     let mut state: State = STATE.load(deps.storage)?;
 
     // permission check
-    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_canonical != state.owner && sender_canonical != state.worker {
         return Err(StdError::generic_err("unauthorized"));
     }
 
@@ -142,8 +137,14 @@ pub fn execute_update_recipient(deps: DepsMut, env: Env, info: MessageInfo, recp
     // update
     if RECIPIENTS.has(deps.storage, recp.clone()) {
         let recp_info = RECIPIENTS.load(deps.storage, recp.clone())?;
+        if amount > recp_info.total_amount && BLOCKLIST.has(deps.storage, recp.clone()) {
+            return Err(StdError::generic_err("blocked"));
+        }
         state.total_vesting_amount = state.total_vesting_amount - recp_info.total_amount;
     } else {
+        if amount > 0 && BLOCKLIST.has(deps.storage, recp.clone()) {
+            return Err(StdError::generic_err("blocked"));
+        }
         state.userlist.push(recp.clone());
     }
     RECIPIENTS.save(deps.storage, recp.clone(), &UserInfo { total_amount: amount, withrawn_amount: 0 })?;
@@ -157,9 +158,34 @@ pub fn execute_update_recipient(deps: DepsMut, env: Env, info: MessageInfo, recp
     Ok(Response::new().add_attribute("method", "update_recipient"))
 }
 
+pub fn execute_reset_recipient(deps: DepsMut, info: MessageInfo, recp: String) -> StdResult<Response> {
+    let mut state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_canonical != state.owner && sender_canonical != state.worker {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // withdrawn tokens stay counted
+    if let Some(mut recp_info) = RECIPIENTS.may_load(deps.storage, recp.clone())? {
+        state.total_vesting_amount -= recp_info.total_amount - recp_info.withrawn_amount;
+        recp_info.total_amount = recp_info.withrawn_amount;
+        RECIPIENTS.save(deps.storage, recp.clone(), &recp_info)?;
+        STATE.save(deps.storage, &state)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "reset_recipient")
+        .add_attribute("recipient", recp))
+}
+
 pub fn execute_withdraw(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
     let state: State = STATE.load(deps.storage)?;
     let sender = info.sender.into_string();
+    if BLOCKLIST.has(deps.storage, sender.clone()) {
+        return Err(StdError::generic_err("blocked"));
+    }
     let mut recpinfo = RECIPIENTS.load(deps.storage, sender.clone())?;
     if recpinfo.total_amount == 0 {
         return Ok(Response::new());
@@ -184,6 +210,106 @@ pub fn execute_withdraw(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult
         .add_attribute("method", "withdraw"))
 }
 
+pub fn execute_set_compliance(deps: DepsMut, info: MessageInfo, compliance: Option<String>) -> StdResult<Response> {
+    let mut state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    state.compliance = match compliance {
+        Some(compliance) => Some(deps.api.addr_canonicalize(compliance.as_str())?),
+        None => None,
+    };
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attribute("method", "set_compliance"))
+}
+
+fn assert_compliance(deps: Deps, info: &MessageInfo) -> StdResult<()> {
+    let state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    let sender_canonical = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_canonical != state.owner && Some(sender_canonical) != state.compliance {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    Ok(())
+}
+
+fn blocklist_event(action: &str, operator: &str, address: &str, reason: u32) -> Event {
+    Event::new("blocklist")
+        .add_attribute("action", action)
+        .add_attribute("operator", operator)
+        .add_attribute("address", address)
+        .add_attribute("reason", reason.to_string())
+}
+
+pub fn execute_block(deps: DepsMut, env: Env, info: MessageInfo, address: String, reason: u32) -> StdResult<Response> {
+    assert_compliance(deps.as_ref(), &info)?;
+
+    BLOCKLIST.save(deps.storage, address.clone(), &BlockInfo { reason, blocked_at: env.block.time.seconds() })?;
+
+    Ok(Response::new()
+        .add_event(blocklist_event("block", info.sender.as_str(), &address, reason))
+        .add_attribute("method", "block"))
+}
+
+pub fn execute_unblock(deps: DepsMut, info: MessageInfo, address: String, reason: u32) -> StdResult<Response> {
+    assert_compliance(deps.as_ref(), &info)?;
+
+    if !BLOCKLIST.has(deps.storage, address.clone()) {
+        return Err(StdError::generic_err("not blocked"));
+    }
+    BLOCKLIST.remove(deps.storage, address.clone());
+
+    Ok(Response::new()
+        .add_event(blocklist_event("unblock", info.sender.as_str(), &address, reason))
+        .add_attribute("method", "unblock"))
+}
+
+pub fn execute_recover_frozen(deps: DepsMut, info: MessageInfo, address: String, recovery: String) -> StdResult<Response> {
+    let mut state: State = STATE.load(deps.storage)?;
+
+    // permission check
+    if deps.api.addr_canonicalize(info.sender.as_str())? != state.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    if !BLOCKLIST.has(deps.storage, address.clone()) {
+        return Err(StdError::generic_err("not blocked"));
+    }
+    let recovery = deps.api.addr_validate(&recovery)?;
+
+    // tokens left, vested or not, leave the vesting schedule
+    let mut recpinfo = RECIPIENTS.load(deps.storage, address.clone())?;
+    let frozen_amount = recpinfo.total_amount - recpinfo.withrawn_amount;
+    if frozen_amount == 0 {
+        return Err(StdError::generic_err("nothing to recover"));
+    }
+    recpinfo.total_amount = recpinfo.withrawn_amount;
+    RECIPIENTS.save(deps.storage, address.clone(), &recpinfo)?;
+
+    state.total_vesting_amount -= frozen_amount;
+    state.distribution_amount -= frozen_amount;
+    STATE.save(deps.storage, &state)?;
+
+    let messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&state.reward_token)?.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recovery.to_string(),
+            amount: Uint128::from(frozen_amount),
+        })?,
+        funds: vec![],
+    })];
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("method", "recover_frozen")
+        .add_attribute("address", address)
+        .add_attribute("amount", frozen_amount.to_string()))
+}
+
 /************************************ Query *************************************/
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -195,6 +321,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Vested { user } => to_binary( &query_vested(deps, _env, user)? ),
         QueryMsg::Locked { user } => to_binary( &query_locked(deps, _env, user)? ),
         QueryMsg::Withdrawable { user } => to_binary( &query_withdrawable(deps, _env, user)? ),
+        QueryMsg::Blocked { address } => to_binary(&BlockedResponse { block: BLOCKLIST.may_load(deps.storage, address)? }),
     }
 }
 
@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::UserInfo;
+use crate::state::{BlockInfo, UserInfo};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -33,7 +33,30 @@ pub enum ExecuteMsg {
     SetStartTime {
         new_start_time: u64
     },
+    /// Zero the tokens left to the recipient, also after the start. Owner or worker only.
+    ResetRecipient {
+        recp: String
+    },
     Withdraw {},
+    /// Set or remove the compliance address, owner only.
+    SetCompliance {
+        compliance: Option<String>,
+    },
+    /// Block the address from new allocations and withdrawals, owner or compliance only.
+    Block {
+        address: String,
+        reason: u32,
+    },
+    /// Lift the block of the address, owner or compliance only.
+    Unblock {
+        address: String,
+        reason: u32,
+    },
+    /// Transfer the tokens left to a blocked address to the recovery address, owner only.
+    RecoverFrozen {
+        address: String,
+        recovery: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -55,7 +78,10 @@ pub enum QueryMsg {
     },
     Withdrawable {
         user: String,
-    }
+    },
+    Blocked {
+        address: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -77,3 +103,8 @@ pub struct GetUserResponse {
 pub struct AmountResponse {
     pub amount: u64,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockedResponse {
+    pub block: Option<BlockInfo>,
+}
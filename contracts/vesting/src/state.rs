@@ -33,6 +33,9 @@ pub struct State {
     pub total_vesting_amount: u64,
     // Participants address list
     pub userlist: Vec<String>,
+    // Compliance address, manages the blocklist with the owner
+    #[serde(default)]
+    pub compliance: Option<CanonicalAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -43,8 +46,18 @@ pub struct UserInfo {
     pub withrawn_amount: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockInfo {
+    // Reason code given when blocked.
+    pub reason: u32,
+    // Timestamp of the block.
+    pub blocked_at: u64,
+}
+
 pub const STATE: Item<State> = Item::new("state");
 
 pub const RECIPIENTS: Map<String, UserInfo> = Map::new("recipients");
 
+pub const BLOCKLIST: Map<String, BlockInfo> = Map::new("blocklist");
+
 pub const ACCURACY: u64 = 1000;
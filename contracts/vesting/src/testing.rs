@@ -1,9 +1,10 @@
 use crate::contract::{execute, instantiate, query};
-use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, AmountResponse, UsersCountResponse, GetUserResponse, GetUsersResponse};
+use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, AmountResponse, BlockedResponse, UsersCountResponse, GetUserResponse, GetUsersResponse};
 use cosmwasm_std::testing::{mock_env, mock_info, mock_dependencies};
 use cosmwasm_std::{
-    from_binary, StdError, Timestamp,
+    from_binary, to_binary, CosmosMsg, StdError, Timestamp, Uint128, WasmMsg,
 };
+use cw20::Cw20ExecuteMsg;
 
 #[test]
 fn test_initialize() {
@@ -306,3 +307,114 @@ fn test_withdraw() {
     assert_eq!(vested.amount, user_vesting_amount);
     assert_eq!(withdrawable.amount, user_vesting_amount - (amount_per_interval * 5 + initial_unlock_amount));
 }
+
+#[test]
+fn test_blocklist() {
+    let mut deps = mock_dependencies();
+    let init_msg = InstantiateMsg {
+        reward_token: "reward_token".to_string(),
+        release_interval: 60,
+        release_rate: 100,
+        initial_unlock: 100,
+        lock_period: 600,
+        vesting_period: 6000,
+        distribution_amount: 1000000,
+    };
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg.clone()).unwrap();
+
+    execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::SetCompliance { compliance: Some("compliance".to_string()) }).unwrap();
+    let msg = ExecuteMsg::UpdateRecipient { recp: "user".to_string(), amount: 1000 };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    // only the owner and compliance manage the blocklist
+    let block_msg = ExecuteMsg::Block { address: "user".to_string(), reason: 7 };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("user", &[]), block_msg.clone());
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "unauthorized"),
+        _ => panic!("Invalid error"),
+    }
+    let res = execute(deps.as_mut(), mock_env(), mock_info("compliance", &[]), block_msg).unwrap();
+    assert_eq!(res.events[0].ty, "blocklist");
+    assert_eq!(res.events[0].attributes[3].value, "7");
+
+    let blocked: BlockedResponse = from_binary(
+        &query(deps.as_ref(), mock_env(), QueryMsg::Blocked { address: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(blocked.block.unwrap().reason, 7);
+
+    // no new allocation nor withdrawal
+    let msg = ExecuteMsg::UpdateRecipient { recp: "user".to_string(), amount: 2000 };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "blocked"),
+        _ => panic!("Invalid error"),
+    }
+
+    let start_time = mock_env().block.time.seconds() + 1;
+    execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::SetStartTime { new_start_time: start_time }).unwrap();
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(start_time + init_msg.lock_period);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), ExecuteMsg::Withdraw {});
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "blocked"),
+        _ => panic!("Invalid error"),
+    }
+
+    // frozen tokens go to the recovery address
+    let msg = ExecuteMsg::RecoverFrozen { address: "user".to_string(), recovery: "recovery".to_string() };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: "recovery".to_string(), amount: Uint128::from(1000u128) }).unwrap(),
+        funds: vec![],
+    }));
+
+    execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Unblock { address: "user".to_string(), reason: 8 }).unwrap();
+    let withdrawable: AmountResponse = from_binary(
+        &query(deps.as_ref(), env, QueryMsg::Withdrawable { user: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(withdrawable.amount, 0);
+}
+
+#[test]
+fn test_reset_recipient() {
+    let mut deps = mock_dependencies();
+    let init_msg = InstantiateMsg {
+        reward_token: "reward_token".to_string(),
+        release_interval: 60,
+        release_rate: 100,
+        initial_unlock: 100,
+        lock_period: 600,
+        vesting_period: 6000,
+        distribution_amount: 1000000,
+    };
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg).unwrap();
+    // no allowance is granted to the worker
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::SetWorker { worker: "presale".to_string() }).unwrap();
+    assert!(res.messages.is_empty());
+
+    let msg = ExecuteMsg::UpdateRecipient { recp: "user".to_string(), amount: 1000 };
+    execute(deps.as_mut(), mock_env(), mock_info("presale", &[]), msg).unwrap();
+    let start_time = mock_env().block.time.seconds() + 1;
+    // the worker starts vesting
+    execute(deps.as_mut(), mock_env(), mock_info("presale", &[]), ExecuteMsg::SetStartTime { new_start_time: start_time }).unwrap();
+
+    // others can't reset
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(start_time + 1);
+    let msg = ExecuteMsg::ResetRecipient { recp: "user".to_string() };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone());
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "unauthorized"),
+        _ => panic!("Invalid error"),
+    }
+
+    // the worker resets entries after the start as well
+    execute(deps.as_mut(), env.clone(), mock_info("presale", &[]), msg).unwrap();
+    let user: GetUserResponse = from_binary(
+        &query(deps.as_ref(), env, QueryMsg::GetUser { user: "user".to_string() }).unwrap(),
+    ).unwrap();
+    assert_eq!(user.data.total_amount, 0);
+}